@@ -0,0 +1,180 @@
+//! Group approvals where several people vote and a quorum decides
+
+use crate::config::ApprovalPolicy;
+use crate::proxy::is_approval;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vote {
+    /// Chat user ID of the voter
+    pub voter_id: String,
+    pub voter_name: Option<String>,
+    pub approve: bool,
+}
+
+impl Vote {
+    /// The voter as shown to the agent and in chat
+    pub fn voter(&self) -> String {
+        match &self.voter_name {
+            Some(name) => format!("{name} ({})", self.voter_id),
+            None => self.voter_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    pub decision: Decision,
+    /// The last vote of everyone who voted, in the order they first voted
+    pub votes: Vec<Vote>,
+}
+
+impl Verdict {
+    pub fn summary(&self) -> String {
+        let votes = self
+            .votes
+            .iter()
+            .map(|vote| format!("{} {}", if vote.approve { "✅" } else { "❌" }, vote.voter()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self.decision {
+            Decision::Approved => format!("Approved. Votes: {votes}"),
+            Decision::Rejected if votes.is_empty() => "Rejected.".to_string(),
+            Decision::Rejected => format!("Rejected. Votes: {votes}"),
+        }
+    }
+}
+
+/// Votes collected so far under a policy. Each approver has one vote, which
+/// they can change until the ballot is decided.
+pub struct Ballot<'a> {
+    policy: &'a ApprovalPolicy,
+    votes: Vec<Vote>,
+}
+
+impl<'a> Ballot<'a> {
+    pub fn new(policy: &'a ApprovalPolicy) -> Self {
+        Self {
+            policy,
+            votes: Vec::new(),
+        }
+    }
+
+    /// Records a vote, ignoring non-approvers, and returns the verdict once decided
+    pub fn cast(&mut self, vote: Vote) -> Option<Verdict> {
+        if !self.policy.is_approver(&vote.voter_id) {
+            return None;
+        }
+        match self.votes.iter_mut().find(|cast| cast.voter_id == vote.voter_id) {
+            Some(cast) => *cast = vote,
+            None => self.votes.push(vote),
+        }
+        let approvals = self.votes.iter().filter(|vote| vote.approve).count();
+        let rejections = self.votes.len() - approvals;
+        let decision = if approvals >= self.policy.quorum {
+            Decision::Approved
+        } else if (self.policy.veto && rejections > 0)
+            || self.policy.approvers.len().saturating_sub(rejections) < self.policy.quorum
+        {
+            Decision::Rejected
+        } else {
+            return None;
+        };
+        Some(Verdict {
+            decision,
+            votes: self.votes.clone(),
+        })
+    }
+
+    /// Chat instructions appended to the question
+    pub fn instructions(&self) -> String {
+        let mut instructions = format!(
+            "Needs {} of {} approvals.",
+            self.policy.quorum,
+            self.policy.approvers.len()
+        );
+        if self.policy.veto {
+            instructions.push_str(" Any rejection denies it.");
+        }
+        instructions
+    }
+}
+
+/// Reads a vote from a chat reply; anything else is discussion and ignored
+pub fn parse_vote(text: &str) -> Option<bool> {
+    if is_approval(text) {
+        return Some(true);
+    }
+    matches!(
+        text.trim().to_lowercase().as_str(),
+        "n" | "no" | "reject" | "rejected" | "deny" | "denied" | "veto"
+    )
+    .then_some(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(veto: bool) -> ApprovalPolicy {
+        ApprovalPolicy {
+            tools: vec!["*".to_string()],
+            approvers: ["a", "b", "c", "d"].map(str::to_string).to_vec(),
+            quorum: 2,
+            veto,
+        }
+    }
+
+    fn vote(voter: &str, approve: bool) -> Vote {
+        Vote {
+            voter_id: voter.to_string(),
+            voter_name: None,
+            approve,
+        }
+    }
+
+    #[test]
+    fn test_quorum() {
+        let policy = policy(true);
+        let mut ballot = Ballot::new(&policy);
+        assert_eq!(ballot.cast(vote("a", true)), None);
+        // Repeated votes from the same person count once, and outsiders don't count
+        assert_eq!(ballot.cast(vote("a", true)), None);
+        assert_eq!(ballot.cast(vote("mallory", true)), None);
+        let verdict = ballot.cast(vote("c", true)).unwrap();
+        assert_eq!(verdict.decision, Decision::Approved);
+        assert_eq!(verdict.summary(), "Approved. Votes: ✅ a, ✅ c");
+    }
+
+    #[test]
+    fn test_veto_and_changed_votes() {
+        let with_veto = policy(true);
+        let mut ballot = Ballot::new(&with_veto);
+        assert_eq!(ballot.cast(vote("a", true)), None);
+        let verdict = ballot.cast(vote("b", false)).unwrap();
+        assert_eq!(verdict.decision, Decision::Rejected);
+
+        let without_veto = policy(false);
+        let mut ballot = Ballot::new(&without_veto);
+        assert_eq!(ballot.cast(vote("a", false)), None);
+        assert_eq!(ballot.cast(vote("a", true)), None);
+        assert_eq!(ballot.cast(vote("b", false)), None);
+        assert_eq!(ballot.cast(vote("c", false)), None);
+        // Only `a` and `d` could still approve, and `d` rejecting ends it
+        let verdict = ballot.cast(vote("d", false)).unwrap();
+        assert_eq!(verdict.decision, Decision::Rejected);
+        assert_eq!(verdict.votes.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_vote() {
+        assert_eq!(parse_vote("LGTM"), Some(true));
+        assert_eq!(parse_vote(" Reject "), Some(false));
+        assert_eq!(parse_vote("why does it need prod access?"), None);
+    }
+}
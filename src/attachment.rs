@@ -0,0 +1,172 @@
+//! Files the human attaches to a reply and how they are handed back to the agent
+
+use base64::Engine;
+use rust_mcp_sdk::schema::{
+    BlobResourceContents, CallToolResultContentItem, EmbeddedResource, ImageContent,
+    TextContent, TextResourceContents,
+};
+
+use crate::config::AttachmentLimits;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Image,
+    Text,
+    Other,
+}
+
+impl AttachmentKind {
+    pub fn detect(content_type: Option<&str>, filename: &str) -> Self {
+        if let Some(content_type) = content_type {
+            let essence = content_type.split(';').next().unwrap_or_default().trim();
+            if essence.starts_with("image/") {
+                return Self::Image;
+            }
+            if essence.starts_with("text/")
+                || matches!(
+                    essence,
+                    "application/json" | "application/xml" | "application/x-yaml" | "application/toml"
+                )
+            {
+                return Self::Text;
+            }
+        }
+        let extension = filename
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "webp" => Self::Image,
+            "txt" | "md" | "log" | "csv" | "json" | "yaml" | "yml" | "toml" | "xml" | "html"
+            | "diff" | "patch" | "rs" | "py" | "js" | "ts" | "go" | "sh" | "sql" => Self::Text,
+            _ => Self::Other,
+        }
+    }
+
+    /// Mime type to report when the chat service didn't provide one
+    fn fallback_mime_type(self) -> &'static str {
+        match self {
+            Self::Image => "image/png",
+            Self::Text => "text/plain",
+            Self::Other => "application/octet-stream",
+        }
+    }
+}
+
+impl AttachmentLimits {
+    /// Largest file of this kind worth downloading; other files are only linked
+    pub fn max_bytes(&self, kind: AttachmentKind) -> u64 {
+        match kind {
+            AttachmentKind::Image => self.max_image_bytes,
+            AttachmentKind::Text => self.max_text_bytes,
+            AttachmentKind::Other => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub url: String,
+    pub content_type: Option<String>,
+    /// Downloaded bytes, or `None` when the file is over the size limit or couldn't be downloaded
+    pub data: Option<Vec<u8>>,
+}
+
+impl Attachment {
+    pub fn kind(&self) -> AttachmentKind {
+        AttachmentKind::detect(self.content_type.as_deref(), &self.filename)
+    }
+
+    /// Images are returned inline, text files as embedded resources and anything
+    /// else (or anything too large) as a link the agent can follow.
+    pub fn into_content(self) -> CallToolResultContentItem {
+        let kind = self.kind();
+        let mime_type = self
+            .content_type
+            .clone()
+            .unwrap_or_else(|| kind.fallback_mime_type().to_string());
+        match (kind, self.data) {
+            (AttachmentKind::Image, Some(data)) => ImageContent::new(
+                base64::engine::general_purpose::STANDARD.encode(data),
+                mime_type,
+                None,
+            )
+            .into(),
+            (AttachmentKind::Text, Some(data)) => {
+                let resource = match String::from_utf8(data) {
+                    Ok(text) => TextResourceContents {
+                        mime_type: Some(mime_type),
+                        text,
+                        uri: self.url,
+                    }
+                    .into(),
+                    Err(e) => BlobResourceContents {
+                        blob: base64::engine::general_purpose::STANDARD.encode(e.into_bytes()),
+                        mime_type: Some(mime_type),
+                        uri: self.url,
+                    }
+                    .into(),
+                };
+                EmbeddedResource::new(resource, None).into()
+            }
+            _ => TextContent::new(
+                format!(
+                    "The human attached `{}` ({mime_type}): {}",
+                    self.filename, self.url
+                ),
+                None,
+            )
+            .into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_mcp_sdk::schema::EmbeddedResourceResource;
+
+    use super::*;
+
+    fn attachment(filename: &str, content_type: Option<&str>, data: Option<&[u8]>) -> Attachment {
+        Attachment {
+            filename: filename.to_string(),
+            url: format!("https://example.com/{filename}"),
+            content_type: content_type.map(str::to_string),
+            data: data.map(<[u8]>::to_vec),
+        }
+    }
+
+    #[test]
+    fn test_detect_kind() {
+        assert_eq!(AttachmentKind::detect(Some("image/png"), "x"), AttachmentKind::Image);
+        assert_eq!(
+            AttachmentKind::detect(Some("text/plain; charset=utf-8"), "x"),
+            AttachmentKind::Text
+        );
+        assert_eq!(AttachmentKind::detect(None, "screenshot.JPG"), AttachmentKind::Image);
+        assert_eq!(AttachmentKind::detect(None, "build.log"), AttachmentKind::Text);
+        assert_eq!(AttachmentKind::detect(None, "archive.zip"), AttachmentKind::Other);
+    }
+
+    #[test]
+    fn test_into_content() {
+        let image = attachment("a.png", Some("image/png"), Some(b"png"));
+        assert!(matches!(image.into_content(), CallToolResultContentItem::ImageContent(_)));
+
+        let text = attachment("a.txt", None, Some(b"hello"));
+        let CallToolResultContentItem::EmbeddedResource(resource) = text.into_content() else {
+            panic!("text attachments should be embedded");
+        };
+        assert!(matches!(
+            resource.resource,
+            EmbeddedResourceResource::TextResourceContents(TextResourceContents { ref text, .. }) if text == "hello"
+        ));
+
+        let too_large = attachment("a.png", Some("image/png"), None);
+        let CallToolResultContentItem::TextContent(link) = too_large.into_content() else {
+            panic!("attachments without data should be linked");
+        };
+        assert!(link.text.contains("https://example.com/a.png"));
+    }
+}
@@ -0,0 +1,44 @@
+//! Append-only record of what the server did on the human's and agent's behalf
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Writes one JSON object per line to the configured file, or to stderr when
+/// no file is configured
+pub struct AuditLog {
+    file: Option<Mutex<File>>,
+}
+
+impl AuditLog {
+    pub fn open(path: Option<&Path>) -> anyhow::Result<Self> {
+        let file = match path {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| anyhow::anyhow!("Failed to open audit log {}: {e}", path.display()))?,
+            )),
+            None => None,
+        };
+        Ok(Self { file })
+    }
+
+    pub fn record(&self, event: &str, details: serde_json::Value) {
+        let entry = serde_json::json!({
+            "time": chrono::Utc::now().to_rfc3339(),
+            "event": event,
+            "details": details,
+        });
+        let Some(file) = &self.file else {
+            eprintln!("📝 {entry}");
+            return;
+        };
+        let mut file = file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(e) = writeln!(file, "{entry}") {
+            eprintln!("❌ Failed to write to the audit log: {e}");
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Several questions asked in one message, answered with numbered replies
+//! that may arrive over several messages
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use rust_mcp_sdk::schema::{CallToolResult, TextContent};
+
+use crate::tools::{Answer, AnswerStatus};
+
+/// Ends a batch early, leaving the remaining questions unanswered
+pub const DONE: &str = "!done";
+
+/// `1. yes`, `2) no` or `3: maybe` at the start of a line, shared with plan reviews
+pub(crate) static NUMBERED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*(\d+)\s*[.):]\s*(.*)$").unwrap());
+
+pub struct Batch {
+    questions: Vec<String>,
+    answers: Vec<Option<Answer>>,
+    done: bool,
+}
+
+impl Batch {
+    pub fn new(questions: Vec<String>) -> Self {
+        let answers = vec![None; questions.len()];
+        Self {
+            questions,
+            answers,
+            done: false,
+        }
+    }
+
+    /// The numbered questions and how to answer them
+    pub fn message(&self) -> String {
+        let mut message = format!("I have {} questions:", self.questions.len());
+        for (i, question) in self.questions.iter().enumerate() {
+            message.push_str(&format!("\n{}. {question}", i + 1));
+        }
+        message.push_str(&format!(
+            "\n_Answer with one numbered line per question, e.g. `1. yes`, in one reply or several. \
+             `2. !skip` skips a question, and `{DONE}` ends without answering the rest._"
+        ));
+        message
+    }
+
+    /// Asks for the questions still open, after `problem` with the last reply when there was one
+    pub fn follow_up(&self, problem: Option<&str>) -> String {
+        let remaining = self.remaining().map(|i| (i + 1).to_string()).collect::<Vec<_>>();
+        let waiting = format!(
+            "Still waiting for answers to {}. Reply with numbered lines, or `{DONE}` to leave them unanswered.",
+            remaining.join(", ")
+        );
+        match problem {
+            Some(problem) => format!("⚠️ {problem} {waiting}"),
+            None => waiting,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done || self.remaining().next().is_none()
+    }
+
+    fn remaining(&self) -> impl Iterator<Item = usize> + '_ {
+        self.answers
+            .iter()
+            .enumerate()
+            .filter(|(_, answer)| answer.is_none())
+            .map(|(i, _)| i)
+    }
+
+    /// Takes in a reply, or explains why it couldn't be matched to the questions.
+    /// A reply without numbers answers the only open question, and `!skip`,
+    /// `!idk` or `!later` on their own apply to all open questions.
+    pub fn record(&mut self, reply: Answer) -> Result<(), String> {
+        if reply.status == AnswerStatus::Answered && reply.text.trim().eq_ignore_ascii_case(DONE) {
+            self.done = true;
+            return Ok(());
+        }
+        let mut items: Vec<(usize, String)> = Vec::new();
+        for line in reply.text.lines() {
+            match NUMBERED.captures(line) {
+                Some(captures) => {
+                    let number = captures[1].parse::<usize>().unwrap_or(0);
+                    if number == 0 || number > self.questions.len() {
+                        return Err(format!("There is no question {}.", &captures[1]));
+                    }
+                    items.push((number - 1, captures[2].to_string()));
+                }
+                // Lines after a numbered one continue its answer
+                None => match items.last_mut() {
+                    Some((_, text)) => {
+                        text.push('\n');
+                        text.push_str(line);
+                    }
+                    None if !line.trim().is_empty() && reply.status == AnswerStatus::Answered => {
+                        return self.record_unnumbered(reply);
+                    }
+                    None => {}
+                },
+            }
+        }
+        if items.is_empty() {
+            return self.record_unnumbered(reply);
+        }
+        for (index, text) in items {
+            self.answers[index] = Some(Answer {
+                attachments: Vec::new(),
+                ..Answer::from_reply(text.trim(), reply.responder.clone())
+            });
+        }
+        Ok(())
+    }
+
+    fn record_unnumbered(&mut self, reply: Answer) -> Result<(), String> {
+        let remaining = self.remaining().collect::<Vec<_>>();
+        match remaining.as_slice() {
+            _ if reply.status != AnswerStatus::Answered => {
+                for index in remaining {
+                    self.answers[index] = Some(reply.clone());
+                }
+            }
+            [index] => self.answers[*index] = Some(reply),
+            _ => return Err("Please number your answers so it's clear which question they are for.".to_string()),
+        }
+        Ok(())
+    }
+
+    /// One entry per question, in order, with `unanswered` for those left open
+    pub fn into_tool_result(self) -> CallToolResult {
+        let items = self
+            .questions
+            .into_iter()
+            .zip(self.answers)
+            .enumerate()
+            .map(|(i, (question, answer))| {
+                let mut item = serde_json::json!({ "number": i + 1, "question": question });
+                match answer {
+                    None => item["status"] = "unanswered".into(),
+                    Some(answer) => {
+                        item["status"] = answer.status.name().into();
+                        item["answer"] = answer.text.into();
+                        item["responder"] = answer.responder.into();
+                        item["answered_at"] = answer.answered_at.to_rfc3339().into();
+                        if let AnswerStatus::Deferred { until: Some(until) } = answer.status {
+                            item["deferred_until"] = until.to_rfc3339().into();
+                        }
+                    }
+                }
+                item
+            })
+            .collect::<Vec<_>>();
+        let text = serde_json::to_string_pretty(&items).unwrap_or_default();
+        let mut meta = serde_json::Map::new();
+        meta.insert("answers".to_string(), items.into());
+        CallToolResult {
+            content: vec![TextContent::new(text, None).into()],
+            is_error: None,
+            meta: Some(meta),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(text: &str) -> Answer {
+        Answer::from_reply(text, Some("U1".to_string()))
+    }
+
+    fn statuses(batch: Batch) -> Vec<(String, Option<String>)> {
+        let result = batch.into_tool_result();
+        let answers = result.meta.unwrap()["answers"].as_array().unwrap().clone();
+        answers
+            .iter()
+            .map(|item| {
+                let answer = item.get("answer").and_then(|answer| answer.as_str()).map(str::to_string);
+                (item["status"].as_str().unwrap().to_string(), answer)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_numbered_replies_over_several_messages() {
+        let mut batch = Batch::new(vec!["Database?".into(), "Region?".into(), "Budget?".into()]);
+        batch.record(reply("1. Postgres\n3) !later 1d ask finance")).unwrap();
+        assert!(!batch.is_finished());
+        assert!(batch.follow_up(None).starts_with("Still waiting for answers to 2."));
+        assert_eq!(
+            batch.record(reply("eu-west-1 or us-east-1")),
+            Ok(()),
+            "the only open question takes an unnumbered reply"
+        );
+        assert!(batch.is_finished());
+        assert_eq!(
+            statuses(batch),
+            [
+                ("answered".to_string(), Some("Postgres".to_string())),
+                ("answered".to_string(), Some("eu-west-1 or us-east-1".to_string())),
+                ("deferred".to_string(), Some("ask finance".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partial_answers() {
+        let mut batch = Batch::new(vec!["Database?".into(), "Region?".into(), "Budget?".into()]);
+        assert!(batch.record(reply("Postgres")).is_err());
+        assert_eq!(batch.record(reply("4. yes")), Err("There is no question 4.".to_string()));
+        // Answers from the Slack form and `/hitl answer` start with `!answer`
+        batch.record(reply("!answer 2. eu-west-1\nmaybe us-east-1 later")).unwrap();
+        batch.record(reply("!DONE")).unwrap();
+        assert!(batch.is_finished());
+        assert_eq!(
+            statuses(batch),
+            [
+                ("unanswered".to_string(), None),
+                ("answered".to_string(), Some("eu-west-1\nmaybe us-east-1 later".to_string())),
+                ("unanswered".to_string(), None),
+            ]
+        );
+
+        let mut batch = Batch::new(vec!["Database?".into(), "Region?".into()]);
+        batch.record(reply("!idk")).unwrap();
+        assert_eq!(statuses(batch)[1].0, "unknown");
+    }
+}
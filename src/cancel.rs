@@ -0,0 +1,191 @@
+//! Stops a tool call when the client sends `notifications/cancelled` for it.
+//! The SDK handles one message at a time and would only see the notification
+//! once the call it cancels had returned, so the transport reads ahead for it.
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use futures::{Stream, StreamExt};
+use rust_mcp_sdk::schema::schema_utils::{ClientMessage, MessageFromServer, NotificationFromClient};
+use rust_mcp_sdk::schema::{ClientNotification, RequestId};
+use rust_mcp_sdk::{IoStream, McpDispatch, MessageDispatcher, Transport, TransportResult};
+use tokio::sync::{mpsc, watch};
+
+/// Cancellations the client sent, matched to the request being handled
+#[derive(Default)]
+pub struct Cancellations {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The request being handled, and the sender that cancels it
+    current: Option<(String, watch::Sender<bool>)>,
+    /// Requests cancelled while still queued behind the current one
+    queued: HashSet<String>,
+}
+
+impl Cancellations {
+    /// Called as the runtime takes up request `id`
+    fn start(&self, id: &RequestId) {
+        let mut state = self.state.lock().unwrap();
+        let key = key(id);
+        let (sender, _) = watch::channel(state.queued.remove(&key));
+        state.current = Some((key, sender));
+    }
+
+    fn cancel(&self, id: &RequestId) {
+        let mut state = self.state.lock().unwrap();
+        let key = key(id);
+        match &state.current {
+            Some((current, sender)) if *current == key => {
+                sender.send_replace(true);
+            }
+            _ => {
+                state.queued.insert(key);
+            }
+        }
+    }
+
+    /// Resolves once the client cancels the request being handled
+    pub fn current(&self) -> impl Future<Output = ()> + Send + 'static {
+        let receiver = self
+            .state
+            .lock()
+            .unwrap()
+            .current
+            .as_ref()
+            .map(|(_, sender)| sender.subscribe());
+        async move {
+            let Some(mut receiver) = receiver else {
+                return std::future::pending().await;
+            };
+            if receiver.wait_for(|cancelled| *cancelled).await.is_err() {
+                std::future::pending().await
+            }
+        }
+    }
+
+    /// Drops a cancellation once the runtime reaches it, by which time the
+    /// request it names has been handled or never existed
+    pub fn forget(&self, id: &RequestId) {
+        self.state.lock().unwrap().queued.remove(&key(id));
+    }
+}
+
+fn key(id: &RequestId) -> String {
+    match id {
+        RequestId::String(id) => format!("s:{id}"),
+        RequestId::Integer(id) => format!("i:{id}"),
+    }
+}
+
+/// A transport that records cancellations as they arrive
+pub struct Cancellable<T> {
+    transport: T,
+    cancellations: Arc<Cancellations>,
+}
+
+impl<T> Cancellable<T> {
+    pub fn new(transport: T, cancellations: Arc<Cancellations>) -> Self {
+        Self { transport, cancellations }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Transport<ClientMessage, MessageFromServer>> Transport<ClientMessage, MessageFromServer> for Cancellable<T> {
+    async fn start(
+        &self,
+    ) -> TransportResult<(
+        Pin<Box<dyn Stream<Item = ClientMessage> + Send>>,
+        MessageDispatcher<ClientMessage>,
+        IoStream,
+    )>
+    where
+        MessageDispatcher<ClientMessage>: McpDispatch<ClientMessage, MessageFromServer>,
+    {
+        let (mut messages, dispatcher, io) = self.transport.start().await?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let cancellations = self.cancellations.clone();
+        tokio::spawn(async move {
+            while let Some(message) = messages.next().await {
+                if let Some(id) = cancelled_request(&message) {
+                    cancellations.cancel(id);
+                }
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        let cancellations = self.cancellations.clone();
+        let messages = futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|message| (message, receiver))
+        })
+        .inspect(move |message| {
+            if let ClientMessage::Request(request) = message {
+                cancellations.start(&request.id);
+            }
+        });
+        Ok((Box::pin(messages), dispatcher, io))
+    }
+
+    async fn shut_down(&self) -> TransportResult<()> {
+        self.transport.shut_down().await
+    }
+
+    async fn is_shut_down(&self) -> bool {
+        self.transport.is_shut_down().await
+    }
+}
+
+/// The request a `notifications/cancelled` message names
+fn cancelled_request(message: &ClientMessage) -> Option<&RequestId> {
+    match message {
+        ClientMessage::Notification(notification) => match &notification.notification {
+            NotificationFromClient::ClientNotification(ClientNotification::CancelledNotification(cancelled)) => {
+                Some(&cancelled.params.request_id)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    async fn is_cancelled(cancellations: &Cancellations) -> bool {
+        tokio::time::timeout(Duration::from_millis(10), cancellations.current())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_cancels_the_request_it_names() {
+        let cancellations = Cancellations::default();
+        assert!(!is_cancelled(&cancellations).await);
+
+        cancellations.start(&RequestId::Integer(1));
+        cancellations.cancel(&RequestId::String("1".to_string()));
+        assert!(!is_cancelled(&cancellations).await);
+        cancellations.cancel(&RequestId::Integer(1));
+        assert!(is_cancelled(&cancellations).await);
+
+        // A request cancelled while queued is cancelled as soon as it starts
+        cancellations.cancel(&RequestId::Integer(3));
+        cancellations.start(&RequestId::Integer(2));
+        assert!(!is_cancelled(&cancellations).await);
+        cancellations.start(&RequestId::Integer(3));
+        assert!(is_cancelled(&cancellations).await);
+
+        cancellations.cancel(&RequestId::Integer(4));
+        cancellations.forget(&RequestId::Integer(4));
+        cancellations.start(&RequestId::Integer(4));
+        assert!(!is_cancelled(&cancellations).await);
+    }
+}
@@ -0,0 +1,338 @@
+//! `/hitl` chat commands for seeing and handling the questions the agent is waiting on
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{oneshot, Mutex};
+
+pub const USAGE: &str =
+    "Usage: `/hitl pending`, `/hitl cancel <id>`, `/hitl answer <id> <text>` or `/hitl history`";
+/// Closed questions listed by `/hitl history`
+const HISTORY_LENGTH: usize = 10;
+/// Longest question or answer excerpt shown in listings
+const EXCERPT_CHARS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HitlCommand {
+    Pending,
+    Cancel(u64),
+    Answer { id: u64, text: String },
+    History,
+}
+
+impl HitlCommand {
+    /// Parses the text typed after `/hitl`; the error is the message to show the human
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (subcommand, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let rest = rest.trim();
+        let (id, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let id = || {
+            id.trim_start_matches('#')
+                .parse::<u64>()
+                .map_err(|_| format!("`{id}` is not a question id. {USAGE}"))
+        };
+        match subcommand {
+            "pending" => Ok(Self::Pending),
+            "history" => Ok(Self::History),
+            "cancel" => Ok(Self::Cancel(id()?)),
+            "answer" if rest.trim().is_empty() => Err(USAGE.to_string()),
+            "answer" => Ok(Self::Answer {
+                id: id()?,
+                text: rest.trim().to_string(),
+            }),
+            _ => Err(USAGE.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Answered(String),
+    /// The human cancelled it with `/hitl cancel`
+    Cancelled,
+    /// The agent stopped waiting, e.g. because the tool call was cancelled
+    Withdrawn,
+}
+
+impl Outcome {
+    /// How the question ended, as shown on the posted question
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Answered(_) => "✅ Answered",
+            Self::Cancelled => "🚫 Cancelled",
+            Self::Withdrawn => "↩️ Withdrawn by the agent",
+        }
+    }
+}
+
+/// Runs `on_drop` when the `ask` holding it is dropped before it finishes,
+/// which is how a backend notices that the agent withdrew its question
+pub struct WithdrawnOnDrop<F: FnOnce()> {
+    on_drop: Option<F>,
+}
+
+impl<F: FnOnce()> WithdrawnOnDrop<F> {
+    pub fn new(on_drop: F) -> Self {
+        Self { on_drop: Some(on_drop) }
+    }
+
+    /// Called once the question has ended in any other way
+    pub fn disarm(mut self) {
+        self.on_drop = None;
+    }
+}
+
+impl<F: FnOnce()> Drop for WithdrawnOnDrop<F> {
+    fn drop(&mut self) {
+        if let Some(on_drop) = self.on_drop.take() {
+            on_drop();
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoggedQuestion {
+    pub id: u64,
+    pub text: String,
+    /// Where the question was posted, in the chat service's link syntax
+    pub link: Option<String>,
+    pub asked_at: DateTime<Utc>,
+    pub closed: Option<(DateTime<Utc>, Outcome)>,
+}
+
+/// Numbers the questions a backend posts so they can be referred to from
+/// commands, hands replies of type `R` to the waiting `ask`, and remembers
+/// the last few questions that were closed
+pub struct QuestionLog<R> {
+    state: Mutex<LogState<R>>,
+}
+
+struct LogState<R> {
+    next_id: u64,
+    questions: VecDeque<LoggedQuestion>,
+    senders: HashMap<u64, oneshot::Sender<R>>,
+}
+
+impl<R> Default for QuestionLog<R> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(LogState {
+                next_id: 0,
+                questions: VecDeque::new(),
+                senders: HashMap::new(),
+            }),
+        }
+    }
+}
+
+impl<R> QuestionLog<R> {
+    /// Records a newly posted question. The receiver gets replies passed to
+    /// [`Self::answer`] and fails once the question is cancelled.
+    pub async fn open(&self, text: &str, link: Option<String>) -> (u64, oneshot::Receiver<R>) {
+        let mut state = self.state.lock().await;
+        state.next_id += 1;
+        let id = state.next_id;
+        state.questions.push_back(LoggedQuestion {
+            id,
+            text: text.to_string(),
+            link,
+            asked_at: Utc::now(),
+            closed: None,
+        });
+        let (sender, receiver) = oneshot::channel();
+        state.senders.insert(id, sender);
+        (id, receiver)
+    }
+
+    /// Sends `reply` to the question's `ask`; `false` when it is no longer pending
+    pub async fn answer(&self, id: u64, reply: R, text: &str) -> bool {
+        let Some(sender) = self.state.lock().await.senders.remove(&id) else {
+            return false;
+        };
+        self.close(id, Outcome::Answered(text.to_string())).await;
+        sender.send(reply).is_ok()
+    }
+
+    /// Withdraws the question, failing its `ask`; `false` when it is no longer pending
+    pub async fn cancel(&self, id: u64) -> bool {
+        if self.state.lock().await.senders.remove(&id).is_none() {
+            return false;
+        }
+        self.close(id, Outcome::Cancelled).await;
+        true
+    }
+
+    /// Records how a question ended when the reply arrived some other way
+    pub async fn close(&self, id: u64, outcome: Outcome) {
+        let mut state = self.state.lock().await;
+        state.senders.remove(&id);
+        if let Some(question) = state.questions.iter_mut().find(|question| question.id == id) {
+            question.closed.get_or_insert((Utc::now(), outcome));
+        }
+        // Only the most recent closed questions are kept for `/hitl history`
+        let closed = state.questions.iter().filter(|question| question.closed.is_some()).count();
+        let mut excess = closed.saturating_sub(HISTORY_LENGTH);
+        state.questions.retain(|question| {
+            if excess > 0 && question.closed.is_some() {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+
+    /// Carries out a command, building the reply for `/hitl answer` with
+    /// `reply`, and returns the Markdown to show the human
+    pub async fn run(&self, command: HitlCommand, reply: impl FnOnce(&str) -> R) -> String {
+        let (id, done) = match command {
+            HitlCommand::Pending => return self.pending_markdown().await,
+            HitlCommand::History => return self.history_markdown().await,
+            HitlCommand::Cancel(id) => (id, self.cancel(id).await.then(|| format!("Cancelled question #{id}."))),
+            HitlCommand::Answer { id, text } => (
+                id,
+                self.answer(id, reply(&text), &text)
+                    .await
+                    .then(|| format!("Sent your answer to question #{id}.")),
+            ),
+        };
+        done.unwrap_or_else(|| format!("Question #{id} is not waiting for an answer. See `/hitl pending`."))
+    }
+
+    /// Markdown listing the open questions, oldest first
+    pub async fn pending_markdown(&self) -> String {
+        let state = self.state.lock().await;
+        let lines = state
+            .questions
+            .iter()
+            .filter(|question| question.closed.is_none())
+            .map(|question| {
+                let mut line = format!(
+                    "- **#{}** asked {}: {}",
+                    question.id,
+                    question.asked_at.format("%H:%M UTC"),
+                    excerpt(&question.text)
+                );
+                if let Some(link) = &question.link {
+                    line.push_str(&format!(" ({link})"));
+                }
+                line
+            })
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            return "Nothing is waiting for an answer.".to_string();
+        }
+        lines.join("\n")
+    }
+
+    /// Markdown listing the recently closed questions, newest first
+    pub async fn history_markdown(&self) -> String {
+        let state = self.state.lock().await;
+        let mut closed = state
+            .questions
+            .iter()
+            .filter_map(|question| question.closed.as_ref().map(|closed| (question, closed)))
+            .collect::<Vec<_>>();
+        closed.sort_by_key(|(_, (closed_at, _))| std::cmp::Reverse(*closed_at));
+        let lines = closed
+            .into_iter()
+            .map(|(question, (closed_at, outcome))| {
+                let outcome = match outcome {
+                    Outcome::Answered(answer) => format!("answered: {}", excerpt(answer)),
+                    Outcome::Cancelled => "cancelled".to_string(),
+                    Outcome::Withdrawn => "withdrawn by the agent".to_string(),
+                };
+                format!(
+                    "- **#{}** {}: {} ({} at {})",
+                    question.id,
+                    question.asked_at.format("%H:%M UTC"),
+                    excerpt(&question.text),
+                    outcome,
+                    closed_at.format("%H:%M UTC")
+                )
+            })
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            return "No question has been answered yet.".to_string();
+        }
+        lines.join("\n")
+    }
+}
+
+/// First line of `text`, shortened for listings
+fn excerpt(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    let mut excerpt = line.chars().take(EXCERPT_CHARS).collect::<String>();
+    if excerpt.len() < text.trim_end().len() {
+        excerpt.push('…');
+    }
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(HitlCommand::parse("pending"), Ok(HitlCommand::Pending));
+        assert_eq!(HitlCommand::parse(" history "), Ok(HitlCommand::History));
+        assert_eq!(HitlCommand::parse("cancel #3"), Ok(HitlCommand::Cancel(3)));
+        assert_eq!(
+            HitlCommand::parse("answer 2 use  the staging db"),
+            Ok(HitlCommand::Answer {
+                id: 2,
+                text: "use  the staging db".to_string()
+            })
+        );
+        assert!(HitlCommand::parse("answer 2").is_err());
+        assert!(HitlCommand::parse("cancel two").is_err());
+        assert!(HitlCommand::parse("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_commands() {
+        let log = QuestionLog::<String>::default();
+        let (first, first_reply) = log.open("Which database?", None).await;
+        let (second, second_reply) = log.open("Deploy now?", Some("<#42>".to_string())).await;
+        assert_eq!((first, second), (1, 2));
+
+        let answer = HitlCommand::Answer {
+            id: first,
+            text: "Postgres".to_string(),
+        };
+        assert!(log.run(answer.clone(), str::to_string).await.contains("Sent"));
+        assert_eq!(first_reply.await.unwrap(), "Postgres");
+        assert!(log.run(answer, str::to_string).await.contains("not waiting"));
+
+        let pending = log.run(HitlCommand::Pending, str::to_string).await;
+        assert_eq!(pending.lines().count(), 1);
+        assert!(pending.contains("Deploy now? (<#42>)"));
+
+        assert!(log.run(HitlCommand::Cancel(second), str::to_string).await.contains("Cancelled"));
+        assert!(second_reply.await.is_err());
+        let history = log.run(HitlCommand::History, str::to_string).await;
+        assert!(history.contains("Deploy now? (cancelled"));
+        assert!(history.contains("answered: Postgres"));
+    }
+
+    #[test]
+    fn test_withdrawn_on_drop() {
+        let withdrawn = std::cell::Cell::new(0);
+        drop(WithdrawnOnDrop::new(|| withdrawn.set(withdrawn.get() + 1)));
+        WithdrawnOnDrop::new(|| withdrawn.set(withdrawn.get() + 1)).disarm();
+        assert_eq!(withdrawn.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_history_is_bounded() {
+        let log = QuestionLog::<String>::default();
+        let (pending, _reply) = log.open("Still open?", None).await;
+        for _ in 0..HISTORY_LENGTH + 5 {
+            let (id, _) = log.open("Again?", None).await;
+            log.cancel(id).await;
+        }
+        assert_eq!(log.history_markdown().await.lines().count(), HISTORY_LENGTH);
+        assert!(log.pending_markdown().await.contains(&format!("#{pending}")));
+    }
+}
@@ -0,0 +1,415 @@
+//! Optional JSON configuration file for settings that don't fit on the command line
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Downstream MCP server whose tools are re-exposed behind human approval
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Size limits for files the human attaches to a reply
+    #[serde(default)]
+    pub attachments: AttachmentLimits,
+    /// Emoji the human can react with to answer without typing
+    #[serde(default)]
+    pub reactions: QuickAnswers,
+    /// Waits before re-mentioning the human about an unanswered question, e.g. `["10m", "1h"]`
+    #[serde(default)]
+    pub reminders: Reminders,
+    /// How long to wait for an answer before giving up, e.g. `"4h"`; forever when omitted
+    #[serde(default, deserialize_with = "optional_duration")]
+    pub answer_timeout: Option<std::time::Duration>,
+    /// Masking of secrets in questions before they are posted
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// File the audit trail is appended to as JSON lines; stderr when omitted
+    #[serde(default)]
+    pub audit_log: Option<PathBuf>,
+    /// Who answers questions for each audience the agent can address
+    #[serde(default)]
+    pub routes: HashMap<String, Route>,
+    /// On-call rotations deciding who is asked at the time of asking
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+}
+
+/// Chat service questions are asked on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Discord,
+    Slack,
+    /// Answers from a file of rules, for testing agents without a chat service
+    Scripted,
+}
+
+impl Platform {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Discord => "Discord",
+            Self::Slack => "Slack",
+            Self::Scripted => "Scripted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    /// Where to ask; the platform selected with `--platform` when omitted
+    #[serde(default)]
+    pub platform: Option<Platform>,
+    /// The person answering, as a user ID on that platform
+    pub user_id: String,
+    /// Channel to ask in; the platform's configured channel when omitted
+    #[serde(default)]
+    pub channel_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// Rotation answering questions without an audience, instead of the configured user
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Rotations by name; each is also an audience the agent can address
+    #[serde(default)]
+    pub rotations: HashMap<String, Rotation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rotation {
+    /// IANA time zone the shifts, working hours and holidays are in, e.g. `Europe/Berlin`
+    pub time_zone: chrono_tz::Tz,
+    /// People taking turns, in order
+    pub members: Vec<Route>,
+    /// Local time the first member's first shift started, e.g. `2026-01-05T09:00:00`
+    pub start: chrono::NaiveDateTime,
+    /// Length of each shift in days
+    #[serde(default = "Rotation::default_shift_days")]
+    pub shift_days: u32,
+    /// When the on-call member can be asked; around the clock when omitted
+    #[serde(default)]
+    pub working_hours: Option<WorkingHours>,
+    /// Local dates on which nobody is asked
+    #[serde(default)]
+    pub holidays: Vec<chrono::NaiveDate>,
+    /// Tell the agent when someone will be available instead of holding the
+    /// question until then
+    #[serde(default)]
+    pub report_unavailable: bool,
+}
+
+impl Rotation {
+    fn default_shift_days() -> u32 {
+        7
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkingHours {
+    #[serde(default = "WorkingHours::default_days")]
+    pub days: Vec<chrono::Weekday>,
+    /// Local start of the working day, e.g. `09:00:00`
+    pub start: chrono::NaiveTime,
+    /// Local end of the working day, e.g. `17:30:00`. An end before the start,
+    /// e.g. `06:00:00` after `22:00:00`, runs past midnight into the next day.
+    pub end: chrono::NaiveTime,
+}
+
+impl WorkingHours {
+    fn default_days() -> Vec<chrono::Weekday> {
+        use chrono::Weekday::*;
+        vec![Mon, Tue, Wed, Thu, Fri]
+    }
+
+    /// The date of the working day that includes a local time, if any. The
+    /// hours after midnight of a window that wraps belong to the day before.
+    pub fn working_day(&self, local: chrono::NaiveDateTime) -> Option<chrono::NaiveDate> {
+        use chrono::Datelike;
+        let (date, time) = (local.date(), local.time());
+        let date = if self.start < self.end {
+            (self.start..self.end).contains(&time).then_some(date)?
+        } else if time >= self.start {
+            date
+        } else if time < self.end {
+            date.pred_opt()?
+        } else {
+            return None;
+        };
+        self.days.contains(&date.weekday()).then_some(date)
+    }
+}
+
+impl Config {
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read config {}: {e}", path.display()))?;
+        let config: Self = serde_json::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Failed to parse config {}: {e}", path.display()))?;
+        config
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid config {}: {e}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Catches settings that parse but can't work
+    fn validate(&self) -> anyhow::Result<()> {
+        for (name, rotation) in &self.schedule.rotations {
+            if let Some(hours) = &rotation.working_hours {
+                if hours.start == hours.end {
+                    anyhow::bail!("Rotation `{name}`: working hours start and end at the same time");
+                }
+            }
+        }
+        if let Some(proxy) = &self.proxy {
+            for (i, policy) in proxy.approval_policies.iter().enumerate() {
+                policy
+                    .validate()
+                    .map_err(|e| anyhow::anyhow!("Approval policy {}: {e}", i + 1))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// Command that launches the downstream MCP server over stdio
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+    /// Tool name patterns (`*` and `?` wildcards) that need a human's approval
+    /// before being forwarded. Every tool is gated when omitted.
+    #[serde(default = "ProxyConfig::default_require_approval")]
+    pub require_approval: Vec<String>,
+    /// Group votes for gated tools; the first policy matching a tool applies,
+    /// and tools matching none are approved by the configured user alone
+    #[serde(default)]
+    pub approval_policies: Vec<ApprovalPolicy>,
+}
+
+impl ProxyConfig {
+    fn default_require_approval() -> Vec<String> {
+        vec!["*".to_string()]
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApprovalPolicy {
+    /// Tool name patterns (`*` and `?` wildcards) the policy applies to
+    #[serde(default = "ProxyConfig::default_require_approval")]
+    pub tools: Vec<String>,
+    /// Chat user IDs allowed to vote
+    pub approvers: Vec<String>,
+    /// Approvals needed for the call to go ahead
+    pub quorum: usize,
+    /// Whether a single rejection denies the call; otherwise it is denied once
+    /// the quorum can no longer be reached
+    #[serde(default = "ApprovalPolicy::default_veto")]
+    pub veto: bool,
+}
+
+impl ApprovalPolicy {
+    fn default_veto() -> bool {
+        true
+    }
+
+    pub fn is_approver(&self, user_id: &str) -> bool {
+        self.approvers.iter().any(|approver| approver == user_id)
+    }
+
+    /// A quorum of zero approves on any vote, and one larger than the approvers
+    /// rejects on every vote
+    fn validate(&self) -> Result<(), String> {
+        if self.quorum == 0 || self.quorum > self.approvers.len() {
+            return Err(format!(
+                "`quorum` must be between 1 and the number of approvers ({}), not {}",
+                self.approvers.len(),
+                self.quorum
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentLimits {
+    /// Largest image that is downloaded and returned inline
+    pub max_image_bytes: u64,
+    /// Largest text file that is downloaded and returned as an embedded resource
+    pub max_text_bytes: u64,
+}
+
+impl Default for AttachmentLimits {
+    fn default() -> Self {
+        Self {
+            max_image_bytes: 5 * 1024 * 1024,
+            max_text_bytes: 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuickAnswer {
+    /// Unicode emoji, e.g. `✅`. Slack also accepts a `:name:`.
+    pub emoji: String,
+    /// Reply the agent receives when the human reacts with `emoji`
+    pub answer: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct QuickAnswers(pub Vec<QuickAnswer>);
+
+impl Default for QuickAnswers {
+    fn default() -> Self {
+        Self(vec![
+            QuickAnswer {
+                emoji: "✅".to_string(),
+                answer: "yes".to_string(),
+            },
+            QuickAnswer {
+                emoji: "❌".to_string(),
+                answer: "no".to_string(),
+            },
+        ])
+    }
+}
+
+/// When to re-mention the human about an unanswered question, written as a
+/// list of waits or as `{ "waits": [...], "max": 3 }`
+#[derive(Debug, Clone, Default)]
+pub struct Reminders {
+    /// Waits between reminders; the last one repeats until the question is answered
+    pub waits: Vec<std::time::Duration>,
+    /// Most reminders sent about one question; unlimited when omitted
+    pub max: Option<u32>,
+    /// Rotation whose working hours reminders to its members wait for
+    pub rotation: Option<Rotation>,
+}
+
+impl Reminders {
+    /// The same reminders, held back outside the working hours of `rotation`
+    pub fn during(&self, rotation: &Rotation) -> Self {
+        Self {
+            rotation: Some(rotation.clone()),
+            ..self.clone()
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Reminders {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Written {
+            Waits(Vec<String>),
+            Limited {
+                waits: Vec<String>,
+                #[serde(default)]
+                max: Option<u32>,
+            },
+        }
+        let (waits, max) = match Written::deserialize(deserializer)? {
+            Written::Waits(waits) => (waits, None),
+            Written::Limited { waits, max } => (waits, max),
+        };
+        let waits = waits
+            .iter()
+            .map(|wait| crate::reminders::parse_duration(wait).map_err(serde::de::Error::custom))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            waits,
+            max,
+            rotation: None,
+        })
+    }
+}
+
+/// Reads a duration written like `30m` or `1h30m`
+fn optional_duration<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<std::time::Duration>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|text| crate::reminders::parse_duration(&text).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactionConfig {
+    /// Built-in secret patterns and the high-entropy check; custom patterns always apply
+    pub enabled: bool,
+    /// Mask long random-looking strings that match no known pattern
+    pub high_entropy: bool,
+    /// Extra regular expressions whose matches are masked
+    pub patterns: Vec<String>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            high_entropy: true,
+            patterns: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy_config(quorum: usize) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "proxy": {
+                "command": "server",
+                "approval_policies": [{ "approvers": ["U1", "U2"], "quorum": quorum }]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rejects_quorum_of_zero() {
+        let error = proxy_config(0).validate().unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Approval policy 1: `quorum` must be between 1 and the number of approvers (2), not 0"
+        );
+    }
+
+    #[test]
+    fn test_rejects_empty_working_hours() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "schedule": { "rotations": { "infra": {
+                "time_zone": "UTC",
+                "members": [{ "user_id": "U1" }],
+                "start": "2026-01-05T09:00:00",
+                "working_hours": { "start": "09:00:00", "end": "09:00:00" }
+            } } }
+        }))
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_rejects_quorum_above_approvers() {
+        assert!(proxy_config(3).validate().is_err());
+        assert!(proxy_config(2).validate().is_ok());
+    }
+}
@@ -0,0 +1,257 @@
+//! Questions the human asks back before answering, which keep the original
+//! question open until the agent has replied and the human gives a final answer
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::tools::{Answer, AnswerStatus, Human, Question};
+
+/// Conversations the agent doesn't reply to for this long are forgotten
+const CONVERSATION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Default)]
+pub struct Conversations {
+    state: Mutex<ConversationState>,
+    /// Listens in the thread while the agent works on its reply, so a final
+    /// answer the human gives in the meantime isn't lost
+    human: Option<Arc<dyn Human>>,
+}
+
+#[derive(Default)]
+struct ConversationState {
+    next_id: u64,
+    open: HashMap<u64, OpenConversation>,
+}
+
+struct OpenConversation {
+    /// The question the conversation is about, pointing at its thread
+    question: Question,
+    /// When the human last asked back
+    since: Instant,
+    /// Waits for a reply starting with `!answer` until the agent replies
+    early_answer: Option<JoinHandle<Option<Answer>>>,
+}
+
+impl OpenConversation {
+    fn expired(&self) -> bool {
+        self.since.elapsed() >= CONVERSATION_TTL
+    }
+
+    fn stop_listening(&mut self) {
+        if let Some(listener) = self.early_answer.take() {
+            listener.abort();
+        }
+    }
+}
+
+impl ConversationState {
+    fn forget_expired(&mut self) {
+        self.open.retain(|_, conversation| {
+            if conversation.expired() {
+                conversation.stop_listening();
+            }
+            !conversation.expired()
+        });
+    }
+}
+
+impl Conversations {
+    pub fn new(human: Arc<dyn Human>) -> Self {
+        Self {
+            state: Mutex::default(),
+            human: Some(human),
+        }
+    }
+
+    /// The question an open conversation is about
+    pub async fn get(&self, id: u64) -> Option<Question> {
+        let mut state = self.state.lock().await;
+        state.forget_expired();
+        state.open.get(&id).map(|conversation| conversation.question.clone())
+    }
+
+    /// The final answer the human gave in conversation `id` before the agent
+    /// replied, if any. Stops listening for one either way.
+    pub async fn take_early_answer(&self, id: u64) -> Option<Answer> {
+        let listener = self.state.lock().await.open.get_mut(&id)?.early_answer.take()?;
+        if !listener.is_finished() {
+            listener.abort();
+            return None;
+        }
+        listener.await.ok().flatten()
+    }
+
+    /// Reads the human's reply to `question`, part of conversation `id` when it
+    /// is a reply to `reply_to_human`. A final answer ends the conversation; in
+    /// a conversation anything else the human writes is a question back, which
+    /// keeps it open.
+    pub async fn settle(&self, mut question: Question, mut answer: Answer, id: Option<u64>) -> Answer {
+        let mut state = self.state.lock().await;
+        state.forget_expired();
+        if let Some(mut conversation) = id.and_then(|id| state.open.remove(&id)) {
+            conversation.stop_listening();
+        }
+        let asks_back = question.conversation && answer.status == AnswerStatus::Answered && !answer.is_final;
+        if !asks_back {
+            return answer;
+        }
+        let id = id.unwrap_or_else(|| {
+            state.next_id += 1;
+            state.next_id
+        });
+        question.thread = answer.thread.clone();
+        let early_answer = self.human.clone().map(|human| {
+            let question = question.clone();
+            tokio::spawn(async move {
+                tokio::time::timeout(CONVERSATION_TTL, final_reply(human.as_ref(), &question))
+                    .await
+                    .ok()
+                    .flatten()
+            })
+        });
+        state.open.insert(
+            id,
+            OpenConversation {
+                question,
+                since: Instant::now(),
+                early_answer,
+            },
+        );
+        answer.status = AnswerStatus::Clarification { conversation_id: id };
+        answer
+    }
+}
+
+/// The first reply in the question's thread that starts with `!answer`. Other
+/// replies are left alone, since they may be about another question there.
+async fn final_reply(human: &dyn Human, question: &Question) -> Option<Answer> {
+    loop {
+        match human.next_reply(question).await {
+            Ok(answer) if answer.is_final => return Some(answer),
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("❌ Stopped listening for an answer in the conversation: {e}");
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_conversation_until_final_answer() {
+        let conversations = Conversations::default();
+        let question = Question {
+            text: "Deploy now?".to_string(),
+            conversation: true,
+            ..Default::default()
+        };
+        let reply = |text: &str| Answer {
+            thread: Some("T1".to_string()),
+            ..Answer::from_reply(text, None)
+        };
+
+        let answer = conversations.settle(question.clone(), reply("Which environment?"), None).await;
+        let AnswerStatus::Clarification { conversation_id } = answer.status else {
+            panic!("not a clarification: {:?}", answer.status);
+        };
+        assert_eq!(answer.text, "Which environment?");
+        let open = conversations.get(conversation_id).await.unwrap();
+        assert_eq!(open.thread.as_deref(), Some("T1"));
+
+        let answer = conversations
+            .settle(open, reply("!ANSWER yes, staging"), Some(conversation_id))
+            .await;
+        assert_eq!((answer.status, answer.text.as_str()), (AnswerStatus::Answered, "yes, staging"));
+        assert!(conversations.get(conversation_id).await.is_none());
+
+        // Without conversation mode the first reply is the answer, with or without `!answer`
+        let plain = Question::new("Deploy now?");
+        let answer = conversations.settle(plain.clone(), reply("Which environment?"), None).await;
+        assert_eq!(answer.status, AnswerStatus::Answered);
+        let answer = conversations.settle(plain, reply("!answer yes"), None).await;
+        assert_eq!(answer.text, "yes");
+    }
+
+    /// A thread where the human posts `replies` without being asked
+    struct Thread(std::sync::Mutex<Vec<&'static str>>);
+
+    #[async_trait::async_trait]
+    impl Human for Thread {
+        async fn ask(&self, _question: &Question) -> anyhow::Result<Answer> {
+            anyhow::bail!("nothing should be asked")
+        }
+
+        async fn start_new_thread(&self, _title: Option<&str>, _topic: Option<&str>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn collect_votes(
+            &self,
+            _question: &Question,
+            _policy: &crate::config::ApprovalPolicy,
+        ) -> anyhow::Result<crate::approval::Verdict> {
+            anyhow::bail!("nothing should be voted on")
+        }
+
+        async fn next_reply(&self, _question: &Question) -> anyhow::Result<Answer> {
+            let reply = self.0.lock().unwrap().pop();
+            match reply {
+                Some(reply) => Ok(Answer::from_reply(reply, None)),
+                None => std::future::pending().await,
+            }
+        }
+    }
+
+    /// Opens a conversation by asking back, returning its ID
+    async fn asked_back(conversations: &Conversations) -> u64 {
+        let question = Question {
+            text: "Deploy now?".to_string(),
+            conversation: true,
+            ..Default::default()
+        };
+        let answer = conversations
+            .settle(question, Answer::from_reply("Which environment?", None), None)
+            .await;
+        match answer.status {
+            AnswerStatus::Clarification { conversation_id } => conversation_id,
+            status => panic!("not a clarification: {status:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keeps_a_final_answer_given_before_the_reply() {
+        // Replies are popped from the end: a remark, then the final answer
+        let conversations = Conversations::new(Arc::new(Thread(std::sync::Mutex::new(vec![
+            "!answer staging",
+            "oh wait",
+        ]))));
+        let id = asked_back(&conversations).await;
+        tokio::task::yield_now().await;
+        let answer = conversations.take_early_answer(id).await.unwrap();
+        assert_eq!((answer.text.as_str(), answer.is_final), ("staging", true));
+
+        let quiet = Conversations::new(Arc::new(Thread(std::sync::Mutex::new(Vec::new()))));
+        let id = asked_back(&quiet).await;
+        assert!(quiet.take_early_answer(id).await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_forgets_abandoned_conversations() {
+        let conversations = Conversations::default();
+        let id = asked_back(&conversations).await;
+        tokio::time::advance(CONVERSATION_TTL - Duration::from_secs(1)).await;
+        assert!(conversations.get(id).await.is_some());
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(conversations.get(id).await.is_none());
+        assert!(conversations.state.lock().await.open.is_empty());
+    }
+}
@@ -1,18 +1,37 @@
+use std::collections::HashMap;
 use std::sync::{Arc, OnceLock};
 
 use serenity::{
     all::{
-        AutoArchiveDuration, ChannelId, ChannelType, Context, CreateMessage, CreateThread,
-        EventHandler, GatewayIntents, Ready, UserId,
+        AutoArchiveDuration, Channel, ChannelId, ChannelType, Colour, Command, CommandInteraction,
+        CommandOptionType, ComponentInteractionCollector, ComponentInteractionDataKind, Context, CreateActionRow,
+        CreateAllowedMentions, CreateAttachment, CreateCommand, CreateCommandOption, CreateEmbed,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu,
+        CreateSelectMenuKind, CreateSelectMenuOption, CreateThread, EditMessage, EditThread, EventHandler,
+        GatewayIntents, Interaction, MessageCollector, MessageId, Reaction, ReactionCollector, ReactionType, Ready,
+        ResolvedValue, UserId,
     },
+    futures::StreamExt,
     Client,
 };
-use tokio::sync::OnceCell;
+use tokio::sync::Mutex;
 
-use crate::tools::Human;
+use crate::approval::{parse_vote, Ballot, Decision, Verdict, Vote};
+use crate::attachment::{Attachment, AttachmentKind};
+use crate::commands::{HitlCommand, Outcome, QuestionLog, WithdrawnOnDrop};
+use crate::config::{ApprovalPolicy, AttachmentLimits, QuickAnswers, Reminders};
+use crate::plan::form_reply;
+use crate::reactions::answer_for;
+use crate::reminders::reminder_text;
+use crate::render::{render, Dialect, Rendered};
+use crate::tools::{Answer, Human, Question, FINAL_ANSWER};
 
 pub async fn start(discord_token: &str, handler: Handler) -> anyhow::Result<()> {
-    let intents = GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT;
+    let intents = GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::DIRECT_MESSAGES
+        | GatewayIntents::MESSAGE_CONTENT
+        | GatewayIntents::GUILD_MESSAGE_REACTIONS
+        | GatewayIntents::DIRECT_MESSAGE_REACTIONS;
     let mut client = Client::builder(discord_token, intents)
         .event_handler(handler)
         .await?;
@@ -22,38 +41,303 @@ pub async fn start(discord_token: &str, handler: Handler) -> anyhow::Result<()>
 #[derive(Clone)]
 pub struct Handler {
     ctx: Arc<OnceLock<Context>>,
+    /// The only user allowed to run `/hitl`
+    user_id: UserId,
+    questions: Arc<QuestionLog<Answer>>,
 }
 
-impl Default for Handler {
-    fn default() -> Self {
+impl Handler {
+    fn new(user_id: UserId) -> Self {
         Self {
             ctx: Arc::new(OnceLock::new()),
+            user_id,
+            questions: Arc::new(QuestionLog::default()),
         }
     }
+
+    async fn run_command(&self, ctx: &Context, interaction: &CommandInteraction) -> anyhow::Result<()> {
+        let content = if interaction.user.id != self.user_id {
+            "Only the person the questions are addressed to can use this command.".to_string()
+        } else {
+            match hitl_command(interaction) {
+                Ok(command) => {
+                    self.questions
+                        // `/hitl answer` always gives the final answer, even in a conversation
+                        .run(command, |text| {
+                            Answer::from_reply(format!("{FINAL_ANSWER} {text}"), Some(self.user_id.to_string()))
+                        })
+                        .await
+                }
+                Err(usage) => usage,
+            }
+        };
+        let content = match render(&content, Dialect::Discord) {
+            Rendered::Messages(messages) => messages.into_iter().next().unwrap_or_default(),
+            Rendered::File { preview, .. } => preview,
+        };
+        interaction
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(content)
+                        .allowed_mentions(CreateAllowedMentions::new())
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
 impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, _: Ready) {
+        if let Err(e) = Command::set_global_commands(&ctx.http, vec![hitl_command_definition()]).await {
+            eprintln!("❌ Failed to register the /hitl command: {e}");
+        }
         self.ctx.set(ctx).ok();
     }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let Interaction::Command(interaction) = interaction else {
+            return;
+        };
+        if interaction.data.name != "hitl" {
+            return;
+        }
+        if let Err(e) = self.run_command(&ctx, &interaction).await {
+            eprintln!("❌ Failed to answer the /hitl command: {e}");
+        }
+    }
+}
+
+fn hitl_command_definition() -> CreateCommand {
+    let id = || CreateCommandOption::new(CommandOptionType::Integer, "id", "Question number from /hitl pending")
+        .min_int_value(1)
+        .required(true);
+    CreateCommand::new("hitl")
+        .description("See and handle the questions the agent is waiting on")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "pending",
+            "List the questions waiting for an answer",
+        ))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "cancel", "Withdraw a question unanswered")
+                .add_sub_option(id()),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "answer", "Answer a question")
+                .add_sub_option(id())
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "text", "Your answer").required(true),
+                ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "history",
+            "List recently closed questions",
+        ))
+}
+
+/// Turns the resolved subcommand options into the same command Slack users type
+fn hitl_command(interaction: &CommandInteraction) -> Result<HitlCommand, String> {
+    let options = interaction.data.options();
+    let Some(subcommand) = options.first() else {
+        return Err(crate::commands::USAGE.to_string());
+    };
+    let ResolvedValue::SubCommand(arguments) = &subcommand.value else {
+        return Err(crate::commands::USAGE.to_string());
+    };
+    let mut text = subcommand.name.to_string();
+    for argument in arguments {
+        match argument.value {
+            ResolvedValue::Integer(id) => text.push_str(&format!(" {id}")),
+            ResolvedValue::String(value) => text.push_str(&format!(" {value}")),
+            _ => {}
+        }
+    }
+    HitlCommand::parse(&text)
+}
+
+/// How questions are grouped into Discord threads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ThreadStrategy {
+    /// One thread titled after the first question, reopened or re-created when archived or deleted
+    #[default]
+    Reuse,
+    /// A new thread for every question
+    Question,
+    /// One thread per MCP session, titled with the session start time
+    Session,
+    /// One thread per topic label supplied by the agent
+    Topic,
 }
 
 pub struct HumanInDiscord {
     user_id: UserId,
-    channel_id: ChannelId,
+    /// Channel whose threads questions are posted in; `None` asks in a direct message
+    channel_id: Option<ChannelId>,
     handler: Handler,
-    thread: OnceCell<ChannelId>,
+    thread_strategy: ThreadStrategy,
+    /// Open threads keyed by topic; strategies without topics use the empty key
+    threads: Mutex<HashMap<String, ChannelId>>,
+    /// Titles requested through `start_new_thread` for the next thread of each topic
+    pending_titles: Mutex<HashMap<String, String>>,
+    session_started: chrono::DateTime<chrono::Utc>,
+    attachment_limits: AttachmentLimits,
+    quick_answers: QuickAnswers,
+    reminders: Reminders,
 }
 
 impl HumanInDiscord {
-    pub fn new(user_id: UserId, channel_id: ChannelId) -> Self {
+    pub fn new(user_id: UserId, channel_id: Option<ChannelId>) -> Self {
+        Self {
+            user_id,
+            channel_id,
+            handler: Handler::new(user_id),
+            thread_strategy: ThreadStrategy::default(),
+            threads: Mutex::new(HashMap::new()),
+            pending_titles: Mutex::new(HashMap::new()),
+            session_started: chrono::Utc::now(),
+            attachment_limits: AttachmentLimits::default(),
+            quick_answers: QuickAnswers::default(),
+            reminders: Reminders::default(),
+        }
+    }
+
+    /// Another human reached through the same bot connection, with threads of its own
+    pub fn for_recipient(&self, user_id: UserId, channel_id: Option<ChannelId>) -> Self {
         Self {
             user_id,
             channel_id,
-            handler: Handler::default(),
-            thread: OnceCell::new(),
+            handler: self.handler.clone(),
+            thread_strategy: self.thread_strategy,
+            threads: Mutex::new(HashMap::new()),
+            pending_titles: Mutex::new(HashMap::new()),
+            session_started: self.session_started,
+            attachment_limits: self.attachment_limits.clone(),
+            quick_answers: self.quick_answers.clone(),
+            reminders: self.reminders.clone(),
+        }
+    }
+
+    /// Discord only reacts with Unicode emoji, so custom `:name:` ones are left out
+    pub fn with_quick_answers(mut self, mut quick_answers: QuickAnswers) -> Self {
+        quick_answers.0.retain(|answer| {
+            let custom = answer.emoji.starts_with(':') && answer.emoji.ends_with(':');
+            if custom {
+                eprintln!("⚠️ Discord can't offer the custom emoji {} as a quick answer; use a Unicode emoji", answer.emoji);
+            }
+            !custom
+        });
+        self.quick_answers = quick_answers;
+        self
+    }
+
+    pub fn with_reminders(mut self, reminders: Reminders) -> Self {
+        self.reminders = reminders;
+        self
+    }
+
+    pub fn with_attachment_limits(mut self, attachment_limits: AttachmentLimits) -> Self {
+        self.attachment_limits = attachment_limits;
+        self
+    }
+
+    pub fn with_thread_strategy(mut self, thread_strategy: ThreadStrategy) -> Self {
+        self.thread_strategy = thread_strategy;
+        self
+    }
+
+    /// The key a question's thread is stored under, or `None` when it always gets a new thread
+    fn thread_key(&self, topic: Option<&str>) -> Option<String> {
+        match self.thread_strategy {
+            ThreadStrategy::Question => None,
+            ThreadStrategy::Reuse | ThreadStrategy::Session => Some(String::new()),
+            ThreadStrategy::Topic => Some(topic.unwrap_or_default().to_string()),
+        }
+    }
+
+    /// The title requested for the next thread of `topic`, once. Strategies
+    /// without a thread key still keep titles apart by topic.
+    async fn take_title(&self, topic: Option<&str>) -> Option<String> {
+        let key = self
+            .thread_key(topic)
+            .unwrap_or_else(|| topic.unwrap_or_default().to_string());
+        self.pending_titles.lock().await.remove(&key)
+    }
+
+    async fn thread_for(&self, ctx: &Context, question: &Question) -> anyhow::Result<ChannelId> {
+        let Some(channel_id) = self.channel_id else {
+            return Ok(self.user_id.create_dm_channel(&ctx.http).await?.id);
+        };
+        // A conversation continues in the thread it started in, while that is still open
+        if let Some(thread) = &question.thread {
+            let thread = ChannelId::new(thread.parse()?);
+            if reopen(ctx, thread).await? {
+                return Ok(thread);
+            }
+        }
+        let key = self.thread_key(question.topic.as_deref());
+        let mut threads = self.threads.lock().await;
+        if let Some(thread) = key.as_ref().and_then(|key| threads.get(key)) {
+            if reopen(ctx, *thread).await? {
+                return Ok(*thread);
+            }
+        }
+
+        let title = match self.take_title(question.topic.as_deref()).await {
+            Some(title) => title,
+            None => match (self.thread_strategy, question.topic.as_deref()) {
+                (ThreadStrategy::Session, _) => format!(
+                    "Agent session {}",
+                    self.session_started.format("%Y-%m-%d %H:%M UTC")
+                ),
+                (ThreadStrategy::Topic, Some(topic)) => topic.to_string(),
+                _ => question.text.clone(),
+            },
+        };
+        let thread_title = title.chars().take(100).collect::<String>();
+        let thread = channel_id
+            .create_thread(
+                &ctx.http,
+                CreateThread::new(thread_title)
+                    .auto_archive_duration(AutoArchiveDuration::OneDay)
+                    .kind(ChannelType::PublicThread),
+            )
+            .await?
+            .id;
+        if let Some(key) = key {
+            threads.insert(key, thread);
         }
+        Ok(thread)
+    }
+
+    /// Downloads an attachment within the size limits. One that can't be
+    /// downloaded is passed on as a link rather than losing the reply.
+    async fn download(&self, attachment: serenity::all::Attachment) -> Attachment {
+        let kind = AttachmentKind::detect(attachment.content_type.as_deref(), &attachment.filename);
+        let data = if u64::from(attachment.size) <= self.attachment_limits.max_bytes(kind) {
+            attachment
+                .download()
+                .await
+                .inspect_err(|e| eprintln!("❌ Failed to download {} from Discord, passing on its link: {e}", attachment.filename))
+                .ok()
+        } else {
+            None
+        };
+        Attachment {
+            filename: attachment.filename,
+            url: attachment.url,
+            content_type: attachment.content_type,
+            data,
+        }
+    }
+
+    pub fn channel_id(&self) -> Option<ChannelId> {
+        self.channel_id
     }
 
     pub fn handler(&self) -> &Handler {
@@ -63,36 +347,519 @@ impl HumanInDiscord {
 
 #[async_trait::async_trait]
 impl Human for HumanInDiscord {
-    async fn ask(&self, question: &str) -> anyhow::Result<String> {
+    async fn ask(&self, question: &Question) -> anyhow::Result<Answer> {
         let ctx = self
             .handler
             .ctx
             .get()
             .ok_or_else(|| anyhow::anyhow!("The connection with Discord is not ready"))?;
-        let thread = self
-            .thread
-            .get_or_try_init(|| async {
-                let thread_title = question.chars().take(100).collect::<String>();
-                let channel = self
-                    .channel_id
-                    .create_thread(
-                        &ctx.http,
-                        CreateThread::new(thread_title)
-                            .auto_archive_duration(AutoArchiveDuration::OneDay)
-                            .kind(ChannelType::PublicThread),
-                    )
-                    .await?;
-                anyhow::Ok(channel.id)
+        let thread = self.thread_for(ctx, question).await?;
+        let message_text = format!("<@{}> {}", self.user_id.get(), question.message_body());
+        let (first_message, last_message) =
+            post(ctx, thread, &message_text, question, only_mentioning([self.user_id])).await?;
+        // The quick answers are offered as reactions so the human only has to tap one
+        let quick_answers = self.quick_answers.for_question(question);
+        for answer in &quick_answers {
+            let reaction = ReactionType::Unicode(answer.emoji.clone());
+            if let Err(e) = thread.create_reaction(&ctx.http, last_message, reaction).await {
+                eprintln!("❌ Failed to add the {} reaction: {e}", answer.emoji);
+            }
+        }
+        // Plan steps are also offered as a menu, all selected at first, when they fit in one
+        let step_menu = match question.steps.len() {
+            0 => None,
+            steps if steps > MAX_MENU_OPTIONS => {
+                eprintln!("⚠️ The plan has {steps} steps, more than a Discord menu holds; it can only be reviewed by replying");
+                None
+            }
+            _ => match thread.send_message(&ctx.http, step_menu(&question.steps)).await {
+                Ok(message) => Some(message.id),
+                Err(e) => {
+                    eprintln!("❌ Failed to add the step menu: {e}");
+                    None
+                }
+            },
+        };
+        let posted = Posted {
+            thread,
+            first_message,
+            last_message,
+            reactions: quick_answers.iter().map(|answer| answer.emoji.clone()).collect(),
+            step_menu,
+        };
+        let (id, command_answer) = self
+            .handler
+            .questions
+            .open(&question.text, Some(format!("<#{}>", thread.get())))
+            .await;
+        let withdrawn = WithdrawnOnDrop::new({
+            let ctx = ctx.clone();
+            let questions = self.handler.questions.clone();
+            let posted = posted.clone();
+            move || {
+                tokio::spawn(async move {
+                    questions.close(id, Outcome::Withdrawn).await;
+                    mark(&ctx, &posted, &Outcome::Withdrawn, None).await;
+                });
+            }
+        });
+        // Our own reminders show up in the thread too
+        let mut collector = thread.await_reply(ctx).filter(|message| !message.author.bot);
+        if self.channel_id.is_none() {
+            collector = collector.author_id(self.user_id);
+        }
+        let mut replies = collector.stream();
+        let user_id = self.user_id;
+        let reaction_answer = |reaction: &Reaction| match &reaction.emoji {
+            ReactionType::Unicode(emoji) => answer_for(&quick_answers, emoji).map(str::to_string),
+            _ => None,
+        };
+        let mut reactions = ReactionCollector::new(ctx)
+            .message_id(last_message)
+            .filter({
+                let quick_answers = quick_answers.clone();
+                move |reaction| {
+                    reaction.user_id == Some(user_id)
+                        && matches!(&reaction.emoji, ReactionType::Unicode(emoji) if answer_for(&quick_answers, emoji).is_some())
+                }
             })
-            .await?;
-        let message_text = format!("<@{}> {question}", self.user_id.get());
-        thread
-            .send_message(&ctx.http, CreateMessage::new().content(message_text))
-            .await?;
-        let message = thread
-            .await_reply(ctx)
+            .stream();
+        let mut selections = step_menu.map(|message_id| {
+            ComponentInteractionCollector::new(ctx)
+                .message_id(message_id)
+                .author_id(user_id)
+                .stream()
+        });
+        let steps = question.steps.len();
+        let selected_steps = |kind: &ComponentInteractionDataKind| match kind {
+            ComponentInteractionDataKind::StringSelect { values } => {
+                let approved = values.iter().filter_map(|value| value.parse().ok()).collect::<Vec<_>>();
+                Some(format!("{FINAL_ANSWER} {}", form_reply(steps, &approved, "")))
+            }
+            _ => None,
+        };
+        let remind = |count| {
+            let message = CreateMessage::new()
+                .content(format!("<@{}> {}", user_id.get(), reminder_text(count)))
+                .allowed_mentions(only_mentioning([user_id]));
+            async move {
+                if let Err(e) = thread.send_message(&ctx.http, message).await {
+                    eprintln!("❌ Failed to send a reminder to Discord: {e}");
+                }
+            }
+        };
+        let reminders = self.reminders.run(remind);
+        tokio::pin!(reminders);
+        let mut command_answer = command_answer;
+        // The human either replies in the thread, reacts, or uses `/hitl answer` / `/hitl cancel`
+        let (text, message) = tokio::select! {
+            never = &mut reminders => match never {},
+            message = replies.next() => match message {
+                Some(message) => (message.content.clone(), Some(message)),
+                None => {
+                    withdrawn.disarm();
+                    self.handler.questions.close(id, Outcome::Withdrawn).await;
+                    mark(ctx, &posted, &Outcome::Withdrawn, None).await;
+                    anyhow::bail!("Failed to await message from the human in Discord");
+                }
+            },
+            Some(text) = async { reactions.next().await.as_ref().and_then(reaction_answer) } => (text, None),
+            Some(text) = async {
+                let interaction = selections.as_mut()?.next().await?;
+                if let Err(e) = interaction.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await {
+                    eprintln!("❌ Failed to acknowledge the step selection: {e}");
+                }
+                selected_steps(&interaction.data.kind)
+            } => (text, None),
+            answer = &mut command_answer => {
+                withdrawn.disarm();
+                let outcome = match &answer {
+                    Ok(answer) => Outcome::Answered(answer.text.clone()),
+                    Err(_) => Outcome::Cancelled,
+                };
+                mark(ctx, &posted, &outcome, Some(self.user_id)).await;
+                return answer
+                    .map(|answer| Answer {
+                        thread: Some(thread.to_string()),
+                        ..answer
+                    })
+                    .map_err(|_| anyhow::anyhow!("The human cancelled the question without answering"));
+            }
+        };
+        withdrawn.disarm();
+        let responder = message.as_ref().map_or(self.user_id, |message| message.author.id);
+        let outcome = Outcome::Answered(Answer::final_text(&text).unwrap_or(&text).to_string());
+        self.handler.questions.close(id, outcome.clone()).await;
+        mark(ctx, &posted, &outcome, Some(responder)).await;
+        let mut attachments = Vec::new();
+        for attachment in message.into_iter().flat_map(|message| message.attachments) {
+            attachments.push(self.download(attachment).await);
+        }
+        Ok(Answer {
+            attachments,
+            thread: Some(thread.to_string()),
+            ..Answer::from_reply(text, Some(responder.to_string()))
+        })
+    }
+
+    async fn start_new_thread(&self, title: Option<&str>, topic: Option<&str>) -> anyhow::Result<()> {
+        let key = self.thread_key(topic);
+        if let Some(key) = &key {
+            self.threads.lock().await.remove(key);
+        }
+        let key = key.unwrap_or_else(|| topic.unwrap_or_default().to_string());
+        let mut pending_titles = self.pending_titles.lock().await;
+        match title {
+            Some(title) => pending_titles.insert(key, title.to_string()),
+            None => pending_titles.remove(&key),
+        };
+        Ok(())
+    }
+
+    async fn collect_votes(&self, question: &Question, policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+        let ctx = self
+            .handler
+            .ctx
+            .get()
+            .ok_or_else(|| anyhow::anyhow!("The connection with Discord is not ready"))?;
+        if self.channel_id.is_none() {
+            anyhow::bail!("Group approvals need a Discord channel the approvers can see");
+        }
+        let approvers = policy
+            .approvers
+            .iter()
+            .map(|approver| {
+                approver
+                    .parse::<UserId>()
+                    .map_err(|_| anyhow::anyhow!("`{approver}` is not a Discord user ID"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut ballot = Ballot::new(policy);
+        let thread = self.thread_for(ctx, question).await?;
+        let mentions = approvers
+            .iter()
+            .map(|approver| format!("<@{}>", approver.get()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let message_text = format!(
+            "{mentions} {}\n\n{} Vote with ✅ or ❌, or reply `approve` or `reject`. You can change your vote until it is decided.",
+            question.message_body(),
+            ballot.instructions()
+        );
+        let (first_message, last_message) =
+            post(ctx, thread, &message_text, question, only_mentioning(approvers.iter().copied())).await?;
+        for emoji in [APPROVE_EMOJI, REJECT_EMOJI] {
+            let reaction = ReactionType::Unicode(emoji.to_string());
+            if let Err(e) = thread.create_reaction(&ctx.http, last_message, reaction).await {
+                eprintln!("❌ Failed to add the {emoji} reaction: {e}");
+            }
+        }
+        let posted = Posted {
+            thread,
+            first_message,
+            last_message,
+            reactions: [APPROVE_EMOJI, REJECT_EMOJI].map(str::to_string).to_vec(),
+            step_menu: None,
+        };
+        let withdrawn = WithdrawnOnDrop::new({
+            let ctx = ctx.clone();
+            let posted = posted.clone();
+            move || {
+                tokio::spawn(async move {
+                    mark(&ctx, &posted, &Outcome::Withdrawn, None).await;
+                });
+            }
+        });
+
+        let is_approver = {
+            let approvers = approvers.clone();
+            move |user_id: UserId| approvers.contains(&user_id)
+        };
+        let mut replies = MessageCollector::new(ctx)
+            .channel_id(thread)
+            .filter({
+                let is_approver = is_approver.clone();
+                move |message| is_approver(message.author.id)
+            })
+            .stream();
+        let mut reactions = ReactionCollector::new(ctx)
+            .message_id(last_message)
+            .filter(move |reaction| reaction.user_id.is_some_and(&is_approver))
+            .stream();
+        let verdict = loop {
+            let vote = tokio::select! {
+                Some(message) = replies.next() => parse_vote(&message.content).map(|approve| Vote {
+                    voter_id: message.author.id.to_string(),
+                    voter_name: Some(message.author.name.clone()),
+                    approve,
+                }),
+                Some(reaction) = reactions.next() => match &reaction.emoji {
+                    ReactionType::Unicode(emoji) if emoji == APPROVE_EMOJI || emoji == REJECT_EMOJI => Some(Vote {
+                        voter_id: reaction.user_id.map(|user_id| user_id.to_string()).unwrap_or_default(),
+                        voter_name: reaction.user(ctx).await.ok().map(|user| user.name),
+                        approve: emoji == APPROVE_EMOJI,
+                    }),
+                    _ => None,
+                },
+                else => {
+                    withdrawn.disarm();
+                    mark(ctx, &posted, &Outcome::Withdrawn, None).await;
+                    anyhow::bail!("Stopped receiving votes from Discord");
+                }
+            };
+            if let Some(verdict) = vote.and_then(|vote| ballot.cast(vote)) {
+                break verdict;
+            }
+        };
+        withdrawn.disarm();
+        let (label, colour) = match verdict.decision {
+            Decision::Approved => ("✅ Approved", Colour::DARK_GREEN),
+            Decision::Rejected => ("❌ Rejected", Colour::RED),
+        };
+        let voters = verdict
+            .votes
+            .iter()
+            .filter(|vote| vote.approve == (verdict.decision == Decision::Approved))
+            .map(|vote| format!("<@{}>", vote.voter_id))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let status = format!("{label} by {voters} <t:{}:f>", chrono::Utc::now().timestamp());
+        set_status(ctx, &posted, status, colour).await;
+        Ok(verdict)
+    }
+
+    async fn next_reply(&self, question: &Question) -> anyhow::Result<Answer> {
+        let ctx = self
+            .handler
+            .ctx
+            .get()
+            .ok_or_else(|| anyhow::anyhow!("The connection with Discord is not ready"))?;
+        let Some(thread) = &question.thread else {
+            return std::future::pending().await;
+        };
+        let thread = ChannelId::new(thread.parse()?);
+        let mut collector = thread.await_reply(ctx).filter(|message| !message.author.bot);
+        if self.channel_id.is_none() {
+            collector = collector.author_id(self.user_id);
+        }
+        let message = collector
+            .stream()
+            .next()
             .await
             .ok_or_else(|| anyhow::anyhow!("Failed to await message from the human in Discord"))?;
-        Ok(message.content)
+        let mut attachments = Vec::new();
+        for attachment in message.attachments {
+            attachments.push(self.download(attachment).await);
+        }
+        Ok(Answer {
+            attachments,
+            thread: Some(thread.to_string()),
+            ..Answer::from_reply(message.content, Some(message.author.id.to_string()))
+        })
+    }
+}
+
+const APPROVE_EMOJI: &str = "✅";
+const REJECT_EMOJI: &str = "❌";
+/// Most options a Discord select menu holds
+const MAX_MENU_OPTIONS: usize = 25;
+/// Longest label of a select menu option
+const MAX_MENU_LABEL_CHARS: usize = 100;
+
+/// A menu of a plan's steps, all selected at first; steps the human
+/// deselects are rejected when they close it
+fn step_menu(steps: &[String]) -> CreateMessage {
+    let options = steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let mut label = format!("{}. {step}", i + 1);
+            if label.chars().count() > MAX_MENU_LABEL_CHARS {
+                label = label.chars().take(MAX_MENU_LABEL_CHARS - 1).collect::<String>() + "…";
+            }
+            CreateSelectMenuOption::new(label, (i + 1).to_string()).default_selection(true)
+        })
+        .collect();
+    let menu = CreateSelectMenu::new("plan_steps", CreateSelectMenuKind::String { options })
+        .placeholder("Steps to approve")
+        .min_values(0)
+        .max_values(steps.len() as u8);
+    CreateMessage::new()
+        .content("Or pick the steps to approve here; deselected steps are rejected.")
+        .components(vec![CreateActionRow::SelectMenu(menu)])
+}
+
+/// Posts a question's text, split or uploaded as needed, and returns its first and last messages
+async fn post(
+    ctx: &Context,
+    thread: ChannelId,
+    message_text: &str,
+    question: &Question,
+    mentions: CreateAllowedMentions,
+) -> anyhow::Result<(MessageId, MessageId)> {
+    let mut files = question
+        .uploads()
+        .map(|attachment| CreateAttachment::bytes(attachment.content.as_bytes(), &attachment.filename))
+        .collect::<Vec<_>>();
+    let messages = match render(message_text, Dialect::Discord) {
+        Rendered::Messages(messages) => messages,
+        Rendered::File { preview, markdown } => {
+            files.insert(0, CreateAttachment::bytes(markdown.into_bytes(), "question.md"));
+            vec![preview]
+        }
+    };
+    // Uploads go with the last message so they sit right above the human's reply
+    let last = messages.len().saturating_sub(1);
+    let mut sent = Vec::new();
+    for (i, content) in messages.into_iter().enumerate() {
+        let mut message = CreateMessage::new()
+            .content(content)
+            .allowed_mentions(mentions.clone());
+        if i == last {
+            message = message.add_files(std::mem::take(&mut files));
+        }
+        sent.push(thread.send_message(&ctx.http, message).await?.id);
+    }
+    match (sent.first(), sent.last()) {
+        (Some(&first), Some(&last)) => Ok((first, last)),
+        _ => anyhow::bail!("Failed to post the question to Discord"),
+    }
+}
+
+/// The agent's text can contain `@everyone`, role or user mentions (its own
+/// or injected from content it read), so outgoing messages may only ping the
+/// people being asked
+fn only_mentioning(user_ids: impl IntoIterator<Item = UserId>) -> CreateAllowedMentions {
+    CreateAllowedMentions::new().users(user_ids)
+}
+
+/// A question as posted in its thread, with what it offers to answer it
+#[derive(Clone)]
+struct Posted {
+    thread: ChannelId,
+    first_message: MessageId,
+    /// The message the reactions are on
+    last_message: MessageId,
+    reactions: Vec<String>,
+    step_menu: Option<MessageId>,
+}
+
+/// Adds an embed to the question's first message saying how it ended, so open
+/// questions stand out when scrolling back through a channel
+async fn mark(ctx: &Context, posted: &Posted, outcome: &Outcome, by: Option<UserId>) {
+    let mut status = outcome.label().to_string();
+    if let Some(by) = by {
+        status.push_str(&format!(" by <@{}>", by.get()));
+    }
+    status.push_str(&format!(" <t:{}:f>", chrono::Utc::now().timestamp()));
+    let colour = match outcome {
+        Outcome::Answered(_) => Colour::DARK_GREEN,
+        Outcome::Cancelled | Outcome::Withdrawn => Colour::LIGHT_GREY,
+    };
+    set_status(ctx, posted, status, colour).await;
+}
+
+/// Shows the status on the question and takes away its reactions and step
+/// menu, so they can't be used to answer a question nobody is waiting on
+async fn set_status(ctx: &Context, posted: &Posted, status: String, colour: Colour) {
+    let thread = posted.thread;
+    let edit = EditMessage::new()
+        .embed(CreateEmbed::new().description(status).colour(colour))
+        .allowed_mentions(CreateAllowedMentions::new());
+    if let Err(e) = thread.edit_message(&ctx.http, posted.first_message, edit).await {
+        eprintln!("❌ Failed to mark the Discord question as closed: {e}");
+    }
+    for emoji in &posted.reactions {
+        let reaction = ReactionType::Unicode(emoji.clone());
+        if let Err(e) = thread.delete_reaction(&ctx.http, posted.last_message, None, reaction).await {
+            eprintln!("❌ Failed to remove the {emoji} reaction: {e}");
+        }
+    }
+    if let Some(step_menu) = posted.step_menu {
+        let edit = EditMessage::new().components(Vec::new());
+        if let Err(e) = thread.edit_message(&ctx.http, step_menu, edit).await {
+            eprintln!("❌ Failed to remove the step menu: {e}");
+        }
+    }
+}
+
+/// Makes sure a previously used thread can still take messages, unarchiving it
+/// when needed. Returns `false` when it is gone or locked and must be replaced.
+async fn reopen(ctx: &Context, thread: ChannelId) -> anyhow::Result<bool> {
+    let Ok(Channel::Guild(channel)) = thread.to_channel(&ctx.http).await else {
+        return Ok(false);
+    };
+    let Some(metadata) = channel.thread_metadata else {
+        return Ok(false);
+    };
+    if metadata.locked {
+        return Ok(false);
+    }
+    if metadata.archived {
+        thread
+            .edit_thread(&ctx.http, EditThread::new().archived(false))
+            .await?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_the_human_is_mentioned() {
+        let mentions = serde_json::to_value(only_mentioning([UserId::new(42)])).unwrap();
+        assert_eq!(mentions["parse"], serde_json::json!([]));
+        assert_eq!(mentions["users"], serde_json::json!(["42"]));
+        assert!(mentions.get("roles").is_none_or(|roles| roles == &serde_json::json!([])));
+    }
+
+    #[test]
+    fn test_step_menu_selects_every_step() {
+        let steps = vec!["Back up the database".to_string(), "x".repeat(200)];
+        let message = serde_json::to_value(step_menu(&steps)).unwrap();
+        let menu = &message["components"][0]["components"][0];
+        assert_eq!((&menu["min_values"], &menu["max_values"]), (&serde_json::json!(0), &serde_json::json!(2)));
+        let options = menu["options"].as_array().unwrap();
+        assert_eq!(options[0]["label"], "1. Back up the database");
+        assert_eq!(options[1]["value"], "2");
+        assert_eq!(options[1]["label"].as_str().unwrap().chars().count(), MAX_MENU_LABEL_CHARS);
+        assert!(options.iter().all(|option| option["default"] == true));
+    }
+
+    fn discord_human(thread_strategy: ThreadStrategy) -> HumanInDiscord {
+        HumanInDiscord::new(UserId::new(42), Some(ChannelId::new(7))).with_thread_strategy(thread_strategy)
+    }
+
+    #[test]
+    fn test_thread_key_follows_the_strategy() {
+        let key = |thread_strategy, topic| discord_human(thread_strategy).thread_key(topic);
+        assert_eq!(key(ThreadStrategy::Question, Some("db")), None);
+        assert_eq!(key(ThreadStrategy::Reuse, Some("db")), Some(String::new()));
+        assert_eq!(key(ThreadStrategy::Session, None), Some(String::new()));
+        assert_eq!(key(ThreadStrategy::Topic, Some("db")), Some("db".to_string()));
+        assert_eq!(key(ThreadStrategy::Topic, None), Some(String::new()));
+    }
+
+    #[tokio::test]
+    async fn test_new_thread_titles_are_kept_per_topic() {
+        let human = discord_human(ThreadStrategy::Topic);
+        human.start_new_thread(Some("Migrating users"), Some("db")).await.unwrap();
+        human.start_new_thread(Some("Rate limits"), Some("api")).await.unwrap();
+        assert_eq!(human.take_title(Some("api")).await.as_deref(), Some("Rate limits"));
+        assert_eq!(human.take_title(Some("api")).await, None);
+        assert_eq!(human.take_title(Some("db")).await.as_deref(), Some("Migrating users"));
+
+        // Without thread keys the next question of the same topic takes the title
+        let human = discord_human(ThreadStrategy::Question);
+        human.start_new_thread(Some("Migrating users"), Some("db")).await.unwrap();
+        assert_eq!(human.take_title(None).await, None);
+        assert_eq!(human.take_title(Some("db")).await.as_deref(), Some("Migrating users"));
+
+        // Strategies with one thread share one title, whatever the topic
+        let human = discord_human(ThreadStrategy::Session);
+        human.start_new_thread(Some("Release"), Some("db")).await.unwrap();
+        assert_eq!(human.take_title(Some("api")).await.as_deref(), Some("Release"));
     }
 }
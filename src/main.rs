@@ -1,11 +1,49 @@
+mod approval;
+mod attachment;
+mod audit;
+mod batch;
+mod cancel;
+mod commands;
+mod config;
+mod conversation;
 mod discord;
 mod slack;
+mod slack_http;
 mod mcp_handler;
+mod plan;
+mod proxy;
+mod reactions;
+mod reminders;
+mod redact;
+mod render;
+mod review;
+mod review_web;
+mod routing;
+mod schedule;
+mod scripted;
+mod timeout;
 mod tools;
+mod validation;
 // use crate::tools::Human; // Removed unused import
 
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use clap::Parser;
-use discord::HumanInDiscord;
+use audit::AuditLog;
+use cancel::{Cancellable, Cancellations};
+use config::{Config, Platform, Reminders, Route};
+use redact::{Redacting, Redactor};
+use discord::{HumanInDiscord, ThreadStrategy};
+use proxy::Proxy;
+use routing::Router;
+use schedule::OnCall;
+use scripted::HumanInScript;
+use slack::HumanInSlack;
+use timeout::TimingOut;
+use tools::Human;
 use rust_mcp_sdk::error::{McpSdkError, SdkResult};
 use rust_mcp_sdk::schema::{
     Implementation, InitializeResult, ServerCapabilities, ServerCapabilitiesTools,
@@ -23,179 +61,390 @@ struct Args {
     // Discord configuration (make optional)
     #[clap(long, env = "DISCORD_TOKEN", help = "Discord bot token")]
     discord_token: Option<String>,
-    #[clap(long, env = "DISCORD_CHANNEL_ID", help = "Discord channel ID")]
+    #[clap(long, env = "DISCORD_CHANNEL_ID", help = "Discord channel ID (not needed with --discord-dm)")]
     discord_channel_id: Option<ChannelId>,
     #[clap(long, env = "DISCORD_USER_ID", help = "Discord user ID")]
     discord_user_id: Option<UserId>,
+    #[clap(
+        long,
+        env = "DISCORD_THREAD_STRATEGY",
+        default_value = "reuse",
+        help = "How questions are grouped into threads: reuse, question, session or topic"
+    )]
+    discord_thread_strategy: ThreadStrategy,
+    #[clap(
+        long,
+        env = "DISCORD_DM",
+        help = "Ask the Discord user in a direct message instead of a channel thread"
+    )]
+    discord_dm: bool,
 
     // Slack configuration (new, optional)
     #[clap(long, env = "SLACK_APP_TOKEN", help = "Slack app-level token for Socket Mode")]
     slack_app_token: Option<String>,
     #[clap(long, env = "SLACK_BOT_TOKEN", help = "Slack bot token")]
     slack_bot_token: Option<String>,
-    #[clap(long, env = "SLACK_CHANNEL_ID", help = "Slack channel ID")]
+    #[clap(long, env = "SLACK_CHANNEL_ID", help = "Slack channel ID (not needed with --slack-dm)")]
     slack_channel_id: Option<String>,
     #[clap(long, env = "SLACK_USER_ID", help = "Slack user ID")]
     slack_user_id: Option<String>,
+    #[clap(
+        long,
+        env = "SLACK_DM",
+        help = "Ask the Slack user in a direct message instead of a channel"
+    )]
+    slack_dm: bool,
+    #[clap(
+        long,
+        env = "SLACK_APP_HOME",
+        help = "List pending questions on the Slack App Home tab"
+    )]
+    slack_app_home: bool,
+    #[clap(
+        long,
+        env = "SLACK_EVENTS_ADDR",
+        help = "Receive Slack events over HTTP on this address (e.g. 0.0.0.0:3000) instead of Socket Mode"
+    )]
+    slack_events_addr: Option<SocketAddr>,
+    #[clap(long, env = "SLACK_SIGNING_SECRET", help = "Slack signing secret for verifying HTTP events")]
+    slack_signing_secret: Option<String>,
+
+    #[clap(
+        long,
+        env = "REVIEW_WEB_ADDR",
+        help = "Serve diffs sent for review as web pages on this address (e.g. 127.0.0.1:8787)"
+    )]
+    review_web_addr: Option<SocketAddr>,
+
+    #[clap(
+        long,
+        env = "HITL_SCRIPT",
+        help = "YAML or JSON file of rules answering questions on the scripted platform"
+    )]
+    script: Option<PathBuf>,
 
     // Platform selection
     #[clap(
         long,
         env = "PLATFORM",
         default_value = "discord",
-        help = "Platform to use: discord or slack"
+        help = "Platform to use: discord, slack or scripted"
     )]
     platform: Platform,
-}
 
-#[derive(Debug, Clone, clap::ValueEnum)]
-enum Platform {
-    Discord,
-    Slack,
+    #[clap(long, env = "HITL_CONFIG", help = "Path to a JSON config file")]
+    config: Option<PathBuf>,
 }
 
-#[tokio::main]
-async fn main() -> SdkResult<()> {
-    let args = Args::parse();
+fn any_error(e: anyhow::Error) -> McpSdkError {
+    McpSdkError::AnyError(e.into_boxed_dyn_error())
+}
 
-    // Basic validation
-    match args.platform {
+/// Checks that everything needed to ask on `platform` was given, exiting otherwise
+fn validate(args: &Args, platform: Platform) {
+    match platform {
         Platform::Discord => {
             if args.discord_token.is_none() {
                 eprintln!("Error: Discord token required when using Discord platform");
                 eprintln!("Set DISCORD_TOKEN environment variable or use --discord-token");
                 std::process::exit(1);
             }
+            if args.discord_user_id.is_none() {
+                eprintln!("Missing required Discord configuration");
+                std::process::exit(1);
+            }
+            if !args.discord_dm && args.discord_channel_id.is_none() {
+                eprintln!("Error: Discord channel ID required unless --discord-dm is set");
+                std::process::exit(1);
+            }
         }
         Platform::Slack => {
-            if args.slack_app_token.is_none() || args.slack_bot_token.is_none() {
-                eprintln!("Error: Slack tokens required when using Slack platform");
-                eprintln!("Set SLACK_APP_TOKEN and SLACK_BOT_TOKEN environment variables");
+            if args.slack_bot_token.is_none() {
+                eprintln!("Error: Slack bot token required when using Slack platform");
+                eprintln!("Set SLACK_BOT_TOKEN environment variable");
+                std::process::exit(1);
+            }
+            if args.slack_events_addr.is_some() {
+                if args.slack_signing_secret.is_none() {
+                    eprintln!("Error: Slack signing secret required when receiving events over HTTP");
+                    eprintln!("Set SLACK_SIGNING_SECRET environment variable");
+                    std::process::exit(1);
+                }
+            } else if args.slack_app_token.is_none() {
+                eprintln!("Error: Slack app token required for Socket Mode");
+                eprintln!("Set SLACK_APP_TOKEN environment variable, or SLACK_EVENTS_ADDR to receive events over HTTP");
+                std::process::exit(1);
+            }
+            if args.slack_user_id.is_none() {
+                eprintln!("❌ Missing required Slack configuration");
+                std::process::exit(1);
+            }
+            if !args.slack_dm && args.slack_channel_id.is_none() {
+                eprintln!("❌ Slack channel ID required unless --slack-dm is set");
+                std::process::exit(1);
+            }
+        }
+        Platform::Scripted => {
+            if args.script.is_none() {
+                eprintln!("Error: Script required when using the scripted platform");
+                eprintln!("Set HITL_SCRIPT environment variable or use --script");
                 std::process::exit(1);
             }
         }
     }
+}
 
-    println!("Starting Human-in-the-Loop MCP server with {} platform",
-             match args.platform {
-                 Platform::Discord => "Discord",
-                 Platform::Slack => "Slack",
-             });
-
-    // For now, continue with existing Discord implementation
-    // This will be updated in later tickets
-    match args.platform {
-        Platform::Discord => {
-            // Existing Discord code...
-            let Args {
-                discord_token: Some(discord_token),
-                discord_channel_id: Some(discord_channel_id),
-                discord_user_id: Some(discord_user_id),
-                ..
-            } = args else {
-                eprintln!("Missing required Discord configuration");
-                std::process::exit(1);
+/// The human answering for an audience, reached through an already configured platform
+fn route_human(
+    route: &Route,
+    default_platform: Platform,
+    discord: Option<&HumanInDiscord>,
+    slack: Option<&HumanInSlack>,
+    scripted: Option<&Arc<HumanInScript>>,
+    reminders: Reminders,
+) -> anyhow::Result<Arc<dyn Human>> {
+    Ok(match (route.platform.unwrap_or(default_platform), discord, slack) {
+        // The script answers for everyone
+        (Platform::Scripted, ..) => match scripted {
+            Some(scripted) => scripted.clone(),
+            None => anyhow::bail!("{} is not configured", Platform::Scripted.name()),
+        },
+        (Platform::Discord, Some(discord), _) => {
+            let user_id = route
+                .user_id
+                .parse::<UserId>()
+                .map_err(|_| anyhow::anyhow!("`{}` is not a Discord user ID", route.user_id))?;
+            let channel_id = match &route.channel_id {
+                Some(channel_id) => Some(
+                    channel_id
+                        .parse::<ChannelId>()
+                        .map_err(|_| anyhow::anyhow!("`{channel_id}` is not a Discord channel ID"))?,
+                ),
+                None => discord.channel_id(),
             };
+            Arc::new(discord.for_recipient(user_id, channel_id).with_reminders(reminders))
+        }
+        (Platform::Slack, _, Some(slack)) => Arc::new(
+            slack
+                .for_recipient(route.user_id.clone(), route.channel_id.clone().or(slack.channel_id()))
+                .with_reminders(reminders),
+        ),
+        (platform, _, _) => anyhow::bail!("{} is not configured", platform.name()),
+    })
+}
 
-            // Continue with existing Discord implementation
-            let human = HumanInDiscord::new(discord_user_id, discord_channel_id);
-            let discord = discord::start(&discord_token, human.handler().clone());
-
-            let server_details = InitializeResult {
-                server_info: Implementation {
-                    name: "Human in the loop".to_string(),
-                    version: "0.1.0".to_string(),
-                },
-                capabilities: ServerCapabilities {
-                    tools: Some(ServerCapabilitiesTools { list_changed: None }),
-                    ..Default::default()
-                },
-                meta: None,
-                instructions: Some(format!(
-                    "This is a Human-in-the-Loop MCP server using {} platform. \
-                     Use the 'ask_human' tool when you need information from humans.",
-                    match args.platform {
-                        Platform::Discord => "Discord",
-                        Platform::Slack => "Slack",
-                    }
-                )),
-                protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
-            };
+#[tokio::main]
+async fn main() -> SdkResult<()> {
+    let args = Args::parse();
+    let config = Config::load(args.config.as_deref()).map_err(any_error)?;
 
-            let transport = StdioTransport::new(TransportOptions::default())?;
-            let server: ServerRuntime = server_runtime::create_server(
-                server_details,
-                transport,
-                mcp_handler::Handler::new(human)
-            );
-            let mcp = server.start();
-
-            tokio::select! {
-                res = mcp => res?,
-                res = discord => res.map_err(|e| McpSdkError::AnyError(e.into_boxed_dyn_error()))?,
-            }
+    // Routes and rotations can reach people on the platform that isn't the default one, which then runs too
+    let uses = |platform: Platform| {
+        args.platform == platform
+            || config
+                .routes
+                .values()
+                .chain(config.schedule.rotations.values().flat_map(|rotation| &rotation.members))
+                .any(|route| route.platform == Some(platform))
+    };
+    for platform in [Platform::Discord, Platform::Slack, Platform::Scripted] {
+        if uses(platform) {
+            validate(&args, platform);
         }
-        Platform::Slack => {
-            println!("🚀 Starting Slack platform...");
-
-            let Args {
-                slack_app_token: Some(slack_app_token),
-                slack_bot_token: Some(slack_bot_token), // This is available from args but not used in the HumanInSlack::new or start_socket_mode yet.
-                slack_channel_id: Some(slack_channel_id),
-                slack_user_id: Some(slack_user_id),
-                ..
-            } = args else {
-                eprintln!("❌ Missing required Slack configuration");
-                std::process::exit(1);
-            };
+    }
 
-            // Create Slack human implementation
-            let slack_human = crate::slack::HumanInSlack::new(slack_user_id, slack_channel_id, slack_bot_token)
-                .map_err(|e| McpSdkError::AnyError(e.into_boxed_dyn_error()))?;
-
-            // Prepare MCP server
-            let server_details = InitializeResult {
-                server_info: Implementation {
-                    name: "Human in the loop".to_string(),
-                    version: "0.1.0".to_string(),
-                },
-                capabilities: ServerCapabilities {
-                    tools: Some(ServerCapabilitiesTools { list_changed: None }),
-                    ..Default::default()
-                },
-                meta: None,
-                instructions: Some(
-                    "This is a Human-in-the-Loop MCP server using Slack platform. \
-                     Use the 'ask_human' tool when you need information from humans.".to_string()
-                ),
-                protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
-            };
+    eprintln!("Starting Human-in-the-Loop MCP server with {} platform", args.platform.name());
 
-            let transport = StdioTransport::new(TransportOptions::default())?;
-            let server: ServerRuntime = server_runtime::create_server(
-                server_details,
-                transport,
-                mcp_handler::Handler::new(slack_human.clone())
-            );
-
-            // Start both MCP server and Slack Socket Mode
-            let mcp_task = server.start();
-            // Clone slack_human again for the new task if it's consumed or needs separate ownership context
-            let slack_task = slack_human.start_socket_mode(slack_app_token);
-
-            println!("✅ Starting MCP server and Slack Socket Mode...");
-
-            tokio::select! {
-                res = mcp_task => {
-                    println!("🛑 MCP server ended: {:?}", res);
-                    res?
-                },
-                res = slack_task => {
-                    println!("🛑 Slack Socket Mode ended: {:?}", res);
-                    res.map_err(|e| McpSdkError::AnyError(e.into_boxed_dyn_error()))?
-                },
-            }
+    let audit = Arc::new(AuditLog::open(config.audit_log.as_deref()).map_err(any_error)?);
+    let redactor = Redactor::new(&config.redaction).map_err(any_error)?;
+
+    let proxy = match &config.proxy {
+        Some(proxy_config) => {
+            eprintln!("Starting downstream MCP server `{}` for approval proxy", proxy_config.command);
+            Some(Proxy::start(proxy_config).await.map_err(any_error)?)
+        }
+        None => None,
+    };
+
+    let discord = match (uses(Platform::Discord), &args.discord_user_id) {
+        (true, Some(discord_user_id)) => Some(
+            HumanInDiscord::new(*discord_user_id, args.discord_channel_id.filter(|_| !args.discord_dm))
+                .with_thread_strategy(args.discord_thread_strategy)
+                .with_attachment_limits(config.attachments.clone())
+                .with_quick_answers(config.reactions.clone())
+                .with_reminders(config.reminders.clone()),
+        ),
+        _ => None,
+    };
+    let slack = match (uses(Platform::Slack), &args.slack_user_id, &args.slack_bot_token) {
+        (true, Some(slack_user_id), Some(slack_bot_token)) => {
+            eprintln!("🚀 Starting Slack platform...");
+            Some(
+                HumanInSlack::new(
+                    slack_user_id.clone(),
+                    args.slack_channel_id.clone().filter(|_| !args.slack_dm),
+                    slack_bot_token.clone(),
+                )
+                .map_err(any_error)?
+                .with_attachment_limits(config.attachments.clone())
+                .with_quick_answers(config.reactions.clone())
+                .with_reminders(config.reminders.clone())
+                .with_app_home(args.slack_app_home),
+            )
+        }
+        _ => None,
+    };
+    let scripted = match (uses(Platform::Scripted), &args.script) {
+        (true, Some(script)) => {
+            eprintln!("📜 Answering from script {}", script.display());
+            Some(Arc::new(HumanInScript::load(script).map_err(any_error)?))
+        }
+        _ => None,
+    };
+
+    let mut routes = HashMap::new();
+    for (audience, route) in &config.routes {
+        let human = route_human(
+            route,
+            args.platform,
+            discord.as_ref(),
+            slack.as_ref(),
+            scripted.as_ref(),
+            config.reminders.clone(),
+        )
+            .map_err(|e| any_error(anyhow::anyhow!("Route `{audience}`: {e}")))?;
+        routes.insert(audience.clone(), human);
+    }
+    for (name, rotation) in &config.schedule.rotations {
+        if routes.contains_key(name) {
+            return Err(any_error(anyhow::anyhow!("`{name}` is both a route and a rotation")));
+        }
+        let on_call = rotation
+            .members
+            .iter()
+            // Members are only reminded during the rotation's working hours
+            .map(|member| {
+                route_human(
+                    member,
+                    args.platform,
+                    discord.as_ref(),
+                    slack.as_ref(),
+                    scripted.as_ref(),
+                    config.reminders.during(rotation),
+                )
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .and_then(|members| OnCall::new(name.clone(), rotation.clone(), members))
+            .map_err(|e| any_error(anyhow::anyhow!("Rotation `{name}`: {e}")))?;
+        routes.insert(name.clone(), Arc::new(on_call));
+    }
+    let discord_handler = discord.as_ref().map(|discord| discord.handler().clone());
+    let slack_listener = slack.clone();
+    let default: Arc<dyn Human> = match (&config.schedule.default, args.platform, discord, slack, scripted) {
+        (Some(rotation), ..) => match config.schedule.rotations.get(rotation).and(routes.get(rotation)) {
+            Some(on_call) => on_call.clone(),
+            None => return Err(any_error(anyhow::anyhow!("Unknown default rotation `{rotation}`"))),
+        },
+        (None, Platform::Discord, Some(discord), ..) => Arc::new(discord),
+        (None, Platform::Slack, _, Some(slack), _) => Arc::new(slack),
+        (None, Platform::Scripted, .., Some(scripted)) => scripted,
+        _ => {
+            eprintln!("Missing required {} configuration", args.platform.name());
+            std::process::exit(1);
+        }
+    };
+    let router = routes
+        .into_iter()
+        .fold(Router::new(default), |router, (audience, human)| router.with_route(audience, human));
+
+    let mut instructions = format!(
+        "This is a Human-in-the-Loop MCP server using {} platform. \
+         Use the 'ask_human' tool when you need information from humans.",
+        args.platform.name()
+    );
+    if !router.audiences().is_empty() {
+        instructions.push_str(&format!(
+            " Set `audience` to route a question to the person responsible for it: {}.",
+            router.audiences().join(", ")
+        ));
+    }
+    let server_details = InitializeResult {
+        server_info: Implementation {
+            name: "Human in the loop".to_string(),
+            version: "0.1.0".to_string(),
+        },
+        capabilities: ServerCapabilities {
+            tools: Some(ServerCapabilitiesTools { list_changed: None }),
+            ..Default::default()
+        },
+        meta: None,
+        instructions: Some(instructions),
+        protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+    };
+
+    let review_pages = match args.review_web_addr {
+        Some(addr) => Some(
+            Redactor::new(&config.redaction)
+                .and_then(|redactor| crate::review_web::ReviewPages::new(addr, redactor))
+                .map_err(any_error)?,
+        ),
+        None => None,
+    };
+    let cancellations = Arc::new(Cancellations::default());
+    let transport = Cancellable::new(StdioTransport::new(TransportOptions::default())?, cancellations.clone());
+    let human = TimingOut::new(Redacting::new(router, redactor, audit.clone()), config.answer_timeout);
+    let server: ServerRuntime = server_runtime::create_server(
+        server_details,
+        transport,
+        mcp_handler::Handler::new(human)
+            .with_proxy(proxy)
+            .with_review_pages(review_pages.clone())
+            .with_cancellations(cancellations),
+    );
+    let mcp_task = server.start();
+    let discord_task = async {
+        match (discord_handler, &args.discord_token) {
+            (Some(handler), Some(discord_token)) => discord::start(discord_token, handler).await,
+            _ => std::future::pending().await,
         }
+    };
+    let slack_task = async {
+        let Some(slack_human) = slack_listener else {
+            return std::future::pending().await;
+        };
+        match (args.slack_events_addr, args.slack_signing_secret.clone(), args.slack_app_token.clone()) {
+            (Some(addr), Some(signing_secret), _) => crate::slack_http::serve(slack_human, addr, signing_secret).await,
+            (_, _, Some(app_token)) => slack_human.start_socket_mode(app_token).await,
+            _ => Err(anyhow::anyhow!("No way to receive Slack events is configured")),
+        }
+    };
+
+    let review_web_task = async {
+        match review_pages {
+            Some(review_pages) => crate::review_web::serve(review_pages).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    eprintln!("✅ Starting MCP server and chat event listeners...");
+
+    tokio::select! {
+        res = mcp_task => {
+            eprintln!("🛑 MCP server ended: {:?}", res);
+            res?
+        },
+        res = discord_task => {
+            eprintln!("🛑 Discord connection ended: {:?}", res);
+            res.map_err(any_error)?
+        },
+        res = slack_task => {
+            eprintln!("🛑 Slack event listener ended: {:?}", res);
+            res.map_err(any_error)?
+        },
+        res = review_web_task => {
+            eprintln!("🛑 Review web view ended: {:?}", res);
+            res.map_err(any_error)?
+        },
     }
 
     Ok(())
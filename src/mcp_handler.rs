@@ -1,18 +1,83 @@
+use std::sync::Arc;
+
 use rust_mcp_sdk::schema::{
-    schema_utils::CallToolError, CallToolRequest, CallToolResult, ListToolsRequest,
-    ListToolsResult, RpcError,
+    schema_utils::CallToolError, CallToolRequest, CallToolResult, CancelledNotification,
+    ListToolsRequest, ListToolsResult, RpcError,
 };
 use rust_mcp_sdk::{mcp_server::ServerHandler, McpServer};
 
+use crate::cancel::Cancellations;
+use crate::conversation::Conversations;
+use crate::proxy::Proxy;
+use crate::review_web::ReviewPages;
 use crate::tools::{Human, HumanTools};
 
 pub struct Handler<H> {
-    human: H,
+    human: Arc<H>,
+    proxy: Option<Proxy>,
+    conversations: Conversations,
+    review_pages: Option<ReviewPages>,
+    cancellations: Arc<Cancellations>,
 }
 
 impl<H: Human> Handler<H> {
     pub fn new(human: H) -> Self {
-        Self { human }
+        let human = Arc::new(human);
+        Self {
+            conversations: Conversations::new(human.clone()),
+            human,
+            proxy: None,
+            review_pages: None,
+            cancellations: Arc::default(),
+        }
+    }
+
+    pub fn with_proxy(mut self, proxy: Option<Proxy>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub fn with_review_pages(mut self, review_pages: Option<ReviewPages>) -> Self {
+        self.review_pages = review_pages;
+        self
+    }
+
+    pub fn with_cancellations(mut self, cancellations: Arc<Cancellations>) -> Self {
+        self.cancellations = cancellations;
+        self
+    }
+
+    async fn call_tool(&self, request: CallToolRequest) -> Result<CallToolResult, CallToolError> {
+        if let Some(proxy) = &self.proxy {
+            let is_own_tool = HumanTools::tools()
+                .iter()
+                .any(|tool| tool.name == request.params.name);
+            if !is_own_tool {
+                return proxy.call_tool(request.params, self.human.as_ref()).await;
+            }
+        }
+
+        let tool_params: HumanTools =
+            HumanTools::try_from(request.params).map_err(CallToolError::new)?;
+
+        match tool_params {
+            HumanTools::AskHumanTool(ask_human_tool) => {
+                ask_human_tool.call_tool(self.human.as_ref(), &self.conversations).await
+            }
+            HumanTools::AskHumanBatchTool(ask_human_batch_tool) => {
+                ask_human_batch_tool.call_tool(self.human.as_ref()).await
+            }
+            HumanTools::ReplyToHumanTool(reply_to_human_tool) => {
+                reply_to_human_tool.call_tool(self.human.as_ref(), &self.conversations).await
+            }
+            HumanTools::ReviewPlanTool(review_plan_tool) => review_plan_tool.call_tool(self.human.as_ref()).await,
+            HumanTools::RequestReviewTool(request_review_tool) => {
+                request_review_tool.call_tool(self.human.as_ref(), self.review_pages.as_ref()).await
+            }
+            HumanTools::StartNewThreadTool(start_new_thread_tool) => {
+                start_new_thread_tool.call_tool(self.human.as_ref()).await
+            }
+        }
     }
 }
 
@@ -24,10 +89,27 @@ impl<H: Human> ServerHandler for Handler<H> {
         request: ListToolsRequest,
         runtime: &dyn McpServer,
     ) -> Result<ListToolsResult, RpcError> {
+        let mut tools = HumanTools::tools();
+        if let Some(proxy) = &self.proxy {
+            let downstream = proxy
+                .list_tools()
+                .await
+                .map_err(|e| RpcError::internal_error().with_message(e.to_string()))?;
+            for tool in downstream {
+                if tools.iter().any(|own| own.name == tool.name) {
+                    eprintln!(
+                        "Warning: downstream tool `{}` is shadowed by a built-in tool",
+                        tool.name
+                    );
+                    continue;
+                }
+                tools.push(tool);
+            }
+        }
         Ok(ListToolsResult {
             meta: None,
             next_cursor: None,
-            tools: HumanTools::tools(),
+            tools,
         })
     }
 
@@ -36,11 +118,25 @@ impl<H: Human> ServerHandler for Handler<H> {
         request: CallToolRequest,
         runtime: &dyn McpServer,
     ) -> Result<CallToolResult, CallToolError> {
-        let tool_params: HumanTools =
-            HumanTools::try_from(request.params).map_err(CallToolError::new)?;
-
-        match tool_params {
-            HumanTools::AskHumanTool(ask_human_tool) => ask_human_tool.call_tool(&self.human).await,
+        let name = request.params.name.clone();
+        // Dropping the call withdraws its open questions in chat
+        tokio::select! {
+            result = self.call_tool(request) => result,
+            () = self.cancellations.current() => {
+                eprintln!("🚫 The client cancelled the `{name}` call");
+                Err(CallToolError(
+                    anyhow::anyhow!("The client cancelled the `{name}` call").into_boxed_dyn_error(),
+                ))
+            }
         }
     }
+
+    async fn handle_cancelled_notification(
+        &self,
+        notification: CancelledNotification,
+        runtime: &dyn McpServer,
+    ) -> Result<(), RpcError> {
+        self.cancellations.forget(&notification.params.request_id);
+        Ok(())
+    }
 }
@@ -0,0 +1,333 @@
+//! Plans the human reviews step by step before the agent carries them out
+
+use rust_mcp_sdk::schema::{CallToolResult, TextContent};
+
+use crate::approval::parse_vote;
+use crate::batch::NUMBERED;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepStatus {
+    Approved,
+    Rejected,
+    /// The human rewrote the step, which approves the new version
+    Edited,
+    /// The human left a note without approving or rejecting the step
+    Commented,
+}
+
+impl StepStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+            Self::Edited => "edited",
+            Self::Commented => "commented",
+        }
+    }
+}
+
+/// What the human said about one step
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StepNote {
+    status: StepStatus,
+    /// The rewritten step for `Edited`, otherwise the comment
+    text: Option<String>,
+}
+
+impl StepNote {
+    /// Reads `approve`, `reject`, `edit <new step>` or a comment, each verdict
+    /// optionally followed by a comment, e.g. `reject: too risky`
+    fn parse(text: &str) -> Self {
+        let text = text.trim();
+        let (word, rest) = text.split_once([' ', ':', ',', '\n']).unwrap_or((text, ""));
+        let rest = rest.trim_start_matches([' ', ':', ',', '-']).trim();
+        let rest = (!rest.is_empty()).then(|| rest.to_string());
+        let status = match word.trim_end_matches(['.', '!']) {
+            "✅" => StepStatus::Approved,
+            "❌" => StepStatus::Rejected,
+            word if word.eq_ignore_ascii_case("edit") => StepStatus::Edited,
+            word => match parse_vote(word) {
+                Some(true) => StepStatus::Approved,
+                Some(false) => StepStatus::Rejected,
+                None => {
+                    return Self {
+                        status: StepStatus::Commented,
+                        text: Some(text.to_string()),
+                    }
+                }
+            },
+        };
+        Self { status, text: rest }
+    }
+
+    /// Combines two notes on the same step, so a verdict and a comment given
+    /// on separate lines both count
+    fn merge(self, later: Self) -> Self {
+        match (&self.status, &later.status) {
+            (StepStatus::Approved | StepStatus::Rejected | StepStatus::Commented, StepStatus::Commented) => Self {
+                text: join(self.text, later.text),
+                ..self
+            },
+            (StepStatus::Commented, StepStatus::Approved | StepStatus::Rejected) => Self {
+                text: join(self.text, later.text),
+                ..later
+            },
+            _ => later,
+        }
+    }
+}
+
+fn join(first: Option<String>, second: Option<String>) -> Option<String> {
+    match (first, second) {
+        (Some(first), Some(second)) => Some(format!("{first}\n{second}")),
+        (first, second) => first.or(second),
+    }
+}
+
+pub struct Plan {
+    title: Option<String>,
+    steps: Vec<String>,
+}
+
+impl Plan {
+    pub fn new(title: Option<String>, steps: Vec<String>) -> Self {
+        Self { title, steps }
+    }
+
+    /// The plan as a numbered checklist and how to review it
+    pub fn message(&self) -> String {
+        let mut message = format!("{}:", self.title.as_deref().unwrap_or("Here is my plan"));
+        for (i, step) in self.steps.iter().enumerate() {
+            message.push_str(&format!("\n☐ {}. {step}", i + 1));
+        }
+        message.push_str(
+            "\n_Reply `approve` or `reject` for the whole plan, or one numbered line per step, \
+             e.g. `2. reject: too risky`, `3. edit: run it on staging first` or `4. check the logs too`. \
+             Steps you don't mention are approved._",
+        );
+        message
+    }
+
+    /// Reads the human's reply, or explains why it couldn't be matched to the steps
+    pub fn review(&self, reply: &str) -> Result<Review, String> {
+        let mut general = String::new();
+        let mut notes: Vec<Option<StepNote>> = vec![None; self.steps.len()];
+        let mut current = None;
+        let mut lines: Vec<(usize, String)> = Vec::new();
+        for line in reply.lines() {
+            match NUMBERED.captures(line) {
+                Some(captures) => {
+                    let number = captures[1].parse::<usize>().unwrap_or(0);
+                    if number == 0 || number > self.steps.len() {
+                        return Err(format!("There is no step {}.", &captures[1]));
+                    }
+                    current = Some(lines.len());
+                    lines.push((number - 1, captures[2].to_string()));
+                }
+                // Lines after a numbered one continue it; lines before any are about the whole plan
+                None => match current {
+                    Some(index) => {
+                        lines[index].1.push('\n');
+                        lines[index].1.push_str(line);
+                    }
+                    None => {
+                        general.push_str(line);
+                        general.push('\n');
+                    }
+                },
+            }
+        }
+        for (index, text) in lines {
+            let note = StepNote::parse(&text);
+            if note.status == StepStatus::Edited && note.text.is_none() {
+                return Err(format!("Say what step {} should become, e.g. `{0}. edit: …`.", index + 1));
+            }
+            notes[index] = Some(match notes[index].take() {
+                Some(earlier) => earlier.merge(note),
+                None => note,
+            });
+        }
+
+        let general = (!general.trim().is_empty()).then(|| StepNote::parse(&general));
+        let (default, comment) = match general {
+            None => (StepStatus::Approved, None),
+            Some(StepNote {
+                status: StepStatus::Edited,
+                ..
+            }) => return Err("Say which step to edit, e.g. `2. edit: …`.".to_string()),
+            Some(note) => (note.status, note.text),
+        };
+        let steps = self
+            .steps
+            .iter()
+            .zip(notes)
+            .map(|(step, note)| {
+                let note = note.unwrap_or(StepNote {
+                    // A comment on the whole plan doesn't hold back the steps
+                    status: match default {
+                        StepStatus::Rejected => StepStatus::Rejected,
+                        _ => StepStatus::Approved,
+                    },
+                    text: None,
+                });
+                match note.status {
+                    StepStatus::Edited => StepReview {
+                        status: note.status,
+                        step: note.text.unwrap_or_default(),
+                        original: Some(step.clone()),
+                        comment: None,
+                    },
+                    status => StepReview {
+                        status,
+                        step: step.clone(),
+                        original: None,
+                        comment: note.text,
+                    },
+                }
+            })
+            .collect();
+        Ok(Review {
+            steps,
+            comment,
+            changes_requested: default == StepStatus::Commented,
+        })
+    }
+}
+
+/// The reply built from the Slack checklist form: steps left unchecked are
+/// rejected, after any notes the human typed
+pub fn form_reply(steps: usize, approved: &[usize], notes: &str) -> String {
+    let mut reply = notes.trim().to_string();
+    for number in (1..=steps).filter(|number| !approved.contains(number)) {
+        reply.push_str(&format!("\n{number}. reject"));
+    }
+    reply.trim().to_string()
+}
+
+pub struct StepReview {
+    pub status: StepStatus,
+    /// The step as it should be carried out, after any edit
+    pub step: String,
+    /// The step as proposed, when the human edited it
+    pub original: Option<String>,
+    pub comment: Option<String>,
+}
+
+pub struct Review {
+    pub steps: Vec<StepReview>,
+    /// A comment on the plan as a whole
+    pub comment: Option<String>,
+    /// Whether the comment on the whole plan asks for changes
+    changes_requested: bool,
+}
+
+impl Review {
+    /// `approved` when every step may go ahead as (re)written, `rejected` when
+    /// none may, and `changes_requested` otherwise
+    pub fn decision(&self) -> &'static str {
+        let status = |status: StepStatus| self.steps.iter().filter(move |step| step.status == status);
+        if status(StepStatus::Rejected).count() == self.steps.len() {
+            "rejected"
+        } else if self.changes_requested
+            || status(StepStatus::Rejected).next().is_some()
+            || status(StepStatus::Commented).next().is_some()
+        {
+            "changes_requested"
+        } else {
+            "approved"
+        }
+    }
+
+    pub fn into_tool_result(self) -> CallToolResult {
+        let decision = self.decision();
+        let steps = self
+            .steps
+            .into_iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let mut item = serde_json::json!({
+                    "number": i + 1,
+                    "step": step.step,
+                    "status": step.status.name(),
+                });
+                if let Some(original) = step.original {
+                    item["original"] = original.into();
+                }
+                if let Some(comment) = step.comment {
+                    item["comment"] = comment.into();
+                }
+                item
+            })
+            .collect::<Vec<_>>();
+        let mut review = serde_json::json!({ "decision": decision, "steps": steps });
+        if let Some(comment) = self.comment {
+            review["comment"] = comment.into();
+        }
+        let text = serde_json::to_string_pretty(&review).unwrap_or_default();
+        let mut meta = serde_json::Map::new();
+        meta.insert("review".to_string(), review);
+        CallToolResult {
+            content: vec![TextContent::new(text, None).into()],
+            is_error: None,
+            meta: Some(meta),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> Plan {
+        Plan::new(
+            None,
+            vec![
+                "Back up the database".to_string(),
+                "Run the migration".to_string(),
+                "Restart the API".to_string(),
+                "Delete the old table".to_string(),
+            ],
+        )
+    }
+
+    fn statuses(review: &Review) -> Vec<&'static str> {
+        review.steps.iter().map(|step| step.status.name()).collect()
+    }
+
+    #[test]
+    fn test_reviews_steps() {
+        let plan = plan();
+        let review = plan.review("lgtm").unwrap();
+        assert_eq!(review.decision(), "approved");
+
+        let review = plan
+            .review("2. edit: run the migration on staging first\n3) ok, but drain it\n4. ❌ keep it a week\nwe need it for audits")
+            .unwrap();
+        assert_eq!(statuses(&review), ["approved", "edited", "approved", "rejected"]);
+        assert_eq!(review.steps[1].step, "run the migration on staging first");
+        assert_eq!(review.steps[1].original.as_deref(), Some("Run the migration"));
+        assert_eq!(review.steps[2].comment.as_deref(), Some("but drain it"));
+        assert_eq!(review.steps[3].comment.as_deref(), Some("keep it a week\nwe need it for audits"));
+        assert_eq!(review.decision(), "changes_requested");
+
+        let review = plan.review("Reject, wrong cluster").unwrap();
+        assert_eq!(review.decision(), "rejected");
+        assert_eq!(review.comment.as_deref(), Some("wrong cluster"));
+
+        let review = plan.review("Add a step to notify support").unwrap();
+        assert_eq!(statuses(&review), ["approved"; 4]);
+        assert_eq!(review.decision(), "changes_requested");
+
+        assert_eq!(plan.review("5. ok").err().as_deref(), Some("There is no step 5."));
+        assert!(plan.review("2. edit").is_err());
+    }
+
+    #[test]
+    fn test_form_reply() {
+        let plan = plan();
+        let reply = form_reply(4, &[1, 2, 3], "4. keep it a week");
+        let review = plan.review(&reply).unwrap();
+        assert_eq!(statuses(&review), ["approved", "approved", "approved", "rejected"]);
+        assert_eq!(review.steps[3].comment.as_deref(), Some("keep it a week"));
+    }
+}
@@ -0,0 +1,241 @@
+//! Approval proxy that fronts a downstream MCP server and asks the human
+//! before forwarding selected tool calls
+
+use std::sync::Arc;
+
+use rust_mcp_sdk::mcp_client::{client_runtime, ClientHandler, ClientRuntime};
+use rust_mcp_sdk::schema::{
+    schema_utils::CallToolError, CallToolRequestParams, CallToolResult, ClientCapabilities,
+    Implementation, InitializeRequestParams, ListToolsRequestParams, TextContent, Tool,
+    LATEST_PROTOCOL_VERSION,
+};
+use rust_mcp_sdk::{McpClient, StdioTransport, TransportOptions};
+
+use crate::approval::Decision;
+use crate::config::{ApprovalPolicy, ProxyConfig};
+use crate::tools::{Human, Question};
+
+struct ProxyClientHandler;
+
+#[async_trait::async_trait]
+impl ClientHandler for ProxyClientHandler {}
+
+pub struct Proxy {
+    client: Arc<ClientRuntime>,
+    require_approval: Vec<String>,
+    approval_policies: Vec<ApprovalPolicy>,
+}
+
+impl Proxy {
+    pub async fn start(config: &ProxyConfig) -> anyhow::Result<Self> {
+        let transport = StdioTransport::create_with_server_launch(
+            &config.command,
+            config.args.clone(),
+            config.env.clone(),
+            TransportOptions::default(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to launch `{}`: {e}", config.command))?;
+        let client_details = InitializeRequestParams {
+            capabilities: ClientCapabilities::default(),
+            client_info: Implementation {
+                name: "Human in the loop".to_string(),
+                version: "0.1.0".to_string(),
+            },
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+        };
+        let client = client_runtime::create_client(client_details, transport, ProxyClientHandler);
+        client
+            .clone()
+            .start()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to initialize `{}`: {e}", config.command))?;
+        Ok(Self {
+            client,
+            require_approval: config.require_approval.clone(),
+            approval_policies: config.approval_policies.clone(),
+        })
+    }
+
+    pub async fn list_tools(&self) -> anyhow::Result<Vec<Tool>> {
+        let mut tools = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self
+                .client
+                .list_tools(cursor.map(|cursor| ListToolsRequestParams {
+                    cursor: Some(cursor),
+                }))
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to list downstream tools: {e}"))?;
+            tools.extend(page.tools);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        Ok(tools)
+    }
+
+    pub fn requires_approval(&self, tool_name: &str) -> bool {
+        self.require_approval
+            .iter()
+            .any(|pattern| matches_pattern(pattern, tool_name))
+    }
+
+    /// The group policy deciding calls to `tool_name`, if any
+    fn approval_policy(&self, tool_name: &str) -> Option<&ApprovalPolicy> {
+        self.approval_policies
+            .iter()
+            .find(|policy| policy.tools.iter().any(|pattern| matches_pattern(pattern, tool_name)))
+    }
+
+    pub async fn call_tool(
+        &self,
+        params: CallToolRequestParams,
+        human: &dyn Human,
+    ) -> Result<CallToolResult, CallToolError> {
+        if self.requires_approval(&params.name) {
+            if let Some(policy) = self.approval_policy(&params.name) {
+                return self.call_tool_with_votes(params, human, policy).await;
+            }
+            let question = approval_question(&params);
+            let answer = human
+                .ask(&Question {
+                    yes_or_no: true,
+                    ..Question::new(question)
+                })
+                .await
+                .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
+            if !is_approval(&answer.text) {
+                let mut result = CallToolResult::text_content(
+                    format!(
+                        "The human denied the call to `{}`. Their reply: {}",
+                        params.name,
+                        answer.text.trim()
+                    ),
+                    None,
+                );
+                result.is_error = Some(true);
+                return Ok(result);
+            }
+        }
+        self.client
+            .call_tool(params)
+            .await
+            .map_err(CallToolError::new)
+    }
+
+    /// Puts the call to the policy's approvers and tells the agent how each voted
+    async fn call_tool_with_votes(
+        &self,
+        params: CallToolRequestParams,
+        human: &dyn Human,
+        policy: &ApprovalPolicy,
+    ) -> Result<CallToolResult, CallToolError> {
+        let question = format!(
+            "The agent wants to call `{}` with these arguments:\n{}",
+            params.name,
+            arguments_block(&params)
+        );
+        let verdict = human
+            .collect_votes(&Question::new(question), policy)
+            .await
+            .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
+        if verdict.decision == Decision::Rejected {
+            let mut result = CallToolResult::text_content(
+                format!("The approvers denied the call to `{}`. {}", params.name, verdict.summary()),
+                None,
+            );
+            result.is_error = Some(true);
+            return Ok(result);
+        }
+        let mut result = self
+            .client
+            .call_tool(params)
+            .await
+            .map_err(CallToolError::new)?;
+        result
+            .content
+            .push(TextContent::new(format!("Approval: {}", verdict.summary()), None).into());
+        Ok(result)
+    }
+}
+
+fn arguments_block(params: &CallToolRequestParams) -> String {
+    let arguments = params
+        .arguments
+        .as_ref()
+        .and_then(|arguments| serde_json::to_string_pretty(arguments).ok())
+        .unwrap_or_else(|| "{}".to_string());
+    format!("```json\n{arguments}\n```")
+}
+
+fn approval_question(params: &CallToolRequestParams) -> String {
+    format!(
+        "The agent wants to call `{}` with these arguments:\n{}\nReply `yes` to approve. Any other reply denies the call and is passed back to the agent.",
+        params.name,
+        arguments_block(params)
+    )
+}
+
+pub fn is_approval(answer: &str) -> bool {
+    matches!(
+        answer.trim().to_lowercase().as_str(),
+        "y" | "yes" | "approve" | "approved" | "ok" | "lgtm"
+    )
+}
+
+/// Matches `name` against a pattern where `*` matches any run of characters
+/// and `?` matches exactly one.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("execute_*", "execute_query"));
+        assert!(matches_pattern("*_file", "write_file"));
+        assert!(matches_pattern("read_?", "read_a"));
+        assert!(matches_pattern("a*b*c", "axxbyyc"));
+        assert!(!matches_pattern("execute_*", "read_query"));
+        assert!(!matches_pattern("read_?", "read_ab"));
+        assert!(!matches_pattern("write_file", "write_files"));
+    }
+
+    #[test]
+    fn test_is_approval() {
+        assert!(is_approval(" Yes \n"));
+        assert!(is_approval("LGTM"));
+        assert!(!is_approval("no"));
+        assert!(!is_approval("yes, but only on staging"));
+    }
+}
@@ -0,0 +1,138 @@
+//! Answers the human gives by tapping a reaction on the question instead of typing
+
+use crate::config::{QuickAnswer, QuickAnswers};
+use crate::tools::Question;
+
+/// Reactions picking the agent's options, in order
+pub const NUMBER_EMOJI: [&str; 10] = [
+    "1\u{fe0f}\u{20e3}",
+    "2\u{fe0f}\u{20e3}",
+    "3\u{fe0f}\u{20e3}",
+    "4\u{fe0f}\u{20e3}",
+    "5\u{fe0f}\u{20e3}",
+    "6\u{fe0f}\u{20e3}",
+    "7\u{fe0f}\u{20e3}",
+    "8\u{fe0f}\u{20e3}",
+    "9\u{fe0f}\u{20e3}",
+    "\u{1f51f}",
+];
+
+/// Slack names its emoji, so reactions arrive as e.g. `white_check_mark`
+const SLACK_NAMES: [(&str, &str); 14] = [
+    ("✅", "white_check_mark"),
+    ("❌", "x"),
+    ("👍", "+1"),
+    ("👎", "-1"),
+    ("1\u{fe0f}\u{20e3}", "one"),
+    ("2\u{fe0f}\u{20e3}", "two"),
+    ("3\u{fe0f}\u{20e3}", "three"),
+    ("4\u{fe0f}\u{20e3}", "four"),
+    ("5\u{fe0f}\u{20e3}", "five"),
+    ("6\u{fe0f}\u{20e3}", "six"),
+    ("7\u{fe0f}\u{20e3}", "seven"),
+    ("8\u{fe0f}\u{20e3}", "eight"),
+    ("9\u{fe0f}\u{20e3}", "nine"),
+    ("\u{1f51f}", "keycap_ten"),
+];
+
+impl QuickAnswers {
+    /// The reactions that answer `question`: its options when it has any,
+    /// otherwise the configured quick answers when `yes` or `no` answers it
+    pub fn for_question(&self, question: &Question) -> Vec<QuickAnswer> {
+        if question.options.is_empty() {
+            return if question.yes_or_no { self.0.clone() } else { Vec::new() };
+        }
+        NUMBER_EMOJI
+            .iter()
+            .zip(&question.options)
+            .map(|(emoji, option)| QuickAnswer {
+                emoji: emoji.to_string(),
+                answer: option.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Words starting a question that `yes` or `no` answers
+const YES_NO_OPENERS: [&str; 29] = [
+    "am", "is", "are", "was", "were", "do", "does", "did", "have", "has", "had", "can", "could", "shall", "should",
+    "will", "would", "may", "might", "must", "isn't", "aren't", "wasn't", "don't", "doesn't", "didn't", "can't",
+    "won't", "shouldn't",
+];
+
+/// Whether the last question in `text` is a yes/no one, like "Should I
+/// deploy now?" rather than "Which region?" or "Postgres or SQLite?"
+pub fn asks_yes_or_no(text: &str) -> bool {
+    let Some((before, _)) = text.rsplit_once('?') else {
+        return false;
+    };
+    let sentence = before
+        .rsplit(['.', '!', '?', '\n', ':'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let mut words = sentence.split_whitespace();
+    let opener = words.next().unwrap_or_default().trim_matches(|c: char| !c.is_alphanumeric() && c != '\'');
+    YES_NO_OPENERS.contains(&opener) && !words.any(|word| word == "or")
+}
+
+/// The answer `emoji` stands for, matching Unicode emoji or Slack names
+pub fn answer_for<'a>(answers: &'a [QuickAnswer], emoji: &str) -> Option<&'a str> {
+    answers
+        .iter()
+        .find(|answer| answer.emoji == emoji || slack_name(&answer.emoji).as_deref() == Some(emoji))
+        .map(|answer| answer.answer.as_str())
+}
+
+/// Slack's name for `emoji`, or `None` when it isn't known
+pub fn slack_name(emoji: &str) -> Option<String> {
+    if let Some(name) = emoji.strip_prefix(':').and_then(|emoji| emoji.strip_suffix(':')) {
+        return Some(name.to_string());
+    }
+    SLACK_NAMES
+        .iter()
+        .find(|(unicode, _)| *unicode == emoji || unicode.trim_end_matches('\u{fe0f}') == emoji)
+        .map(|(_, name)| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_replace_quick_answers() {
+        let mut question = Question {
+            yes_or_no: true,
+            ..Question::new("Use the default database?")
+        };
+        let defaults = QuickAnswers::default().for_question(&question);
+        assert_eq!(answer_for(&defaults, "✅"), Some("yes"));
+        assert_eq!(answer_for(&defaults, "x"), Some("no"));
+
+        question.options = vec!["Postgres".to_string(), "SQLite".to_string()];
+        let options = QuickAnswers::default().for_question(&question);
+        assert_eq!(answer_for(&options, NUMBER_EMOJI[1]), Some("SQLite"));
+        assert_eq!(answer_for(&options, "one"), Some("Postgres"));
+        assert_eq!(answer_for(&options, "✅"), None);
+        assert_eq!(answer_for(&options, "three"), None);
+    }
+
+    #[test]
+    fn test_quick_answers_only_for_yes_or_no_questions() {
+        assert!(QuickAnswers::default().for_question(&Question::new("Which database?")).is_empty());
+        assert!(asks_yes_or_no("Should I deploy now?"));
+        assert!(asks_yes_or_no("The tests pass. Can I merge it?"));
+        assert!(asks_yes_or_no("Don't we need a migration?"));
+        assert!(!asks_yes_or_no("Which region?"));
+        assert!(!asks_yes_or_no("Should I use Postgres or SQLite?"));
+        assert!(!asks_yes_or_no("Is it ready? What's left?"));
+        assert!(!asks_yes_or_no("Please describe the setup"));
+    }
+
+    #[test]
+    fn test_slack_name() {
+        assert_eq!(slack_name("🔟").as_deref(), Some("keycap_ten"));
+        assert_eq!(slack_name(":ship_it:").as_deref(), Some("ship_it"));
+        assert_eq!(slack_name("🦀"), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! Re-mentions the human about questions left unanswered
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::config::Reminders;
+
+/// Parses a duration such as `90s`, `10m`, `2h` or `1h30m`
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("`{text}` is not a duration like `10m`, `2h` or `1h30m`");
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let value = number.parse::<u64>().map_err(|_| invalid())?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+impl Reminders {
+    /// Waits before each reminder: the configured ones in turn, then the last
+    /// until `max` reminders were sent
+    fn waits(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.waits
+            .iter()
+            .copied()
+            .chain(self.waits.last().copied().into_iter().cycle())
+            .take(self.max.map_or(usize::MAX, |max| max as usize))
+    }
+
+    /// How much longer a reminder due at `now` waits for the rotation's working hours
+    fn postponed(&self, now: DateTime<Utc>) -> Duration {
+        self.rotation
+            .as_ref()
+            .and_then(|rotation| rotation.next_available(now))
+            .and_then(|from| (from - now).to_std().ok())
+            .unwrap_or_default()
+    }
+
+    /// Calls `remind` with the reminder's number after each wait, holding
+    /// reminders that fall outside working hours until they start. Runs until
+    /// dropped, so it is raced against the answer.
+    pub async fn run<F, Fut>(&self, mut remind: F) -> Infallible
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        for (count, wait) in (1..).zip(self.waits()) {
+            tokio::time::sleep(wait).await;
+            tokio::time::sleep(self.postponed(Utc::now())).await;
+            remind(count).await;
+        }
+        std::future::pending().await
+    }
+}
+
+/// Text of a reminder, which follows the mention
+pub fn reminder_text(count: u32) -> String {
+    let times = if count == 1 { "once".to_string() } else { format!("{count} times") };
+    format!("⏰ This question is still waiting for your answer (reminded {times}).")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration(" 2d "), Ok(Duration::from_secs(2 * 86400)));
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("0m").is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_last_wait_repeats() {
+        let reminders = Reminders {
+            waits: vec![Duration::from_secs(600), Duration::from_secs(3600)],
+            ..Default::default()
+        };
+        let sent = std::cell::RefCell::new(Vec::new());
+        let run = reminders.run(|count| {
+            sent.borrow_mut().push((count, tokio::time::Instant::now()));
+            async {}
+        });
+        let start = tokio::time::Instant::now();
+        let _ = tokio::time::timeout(Duration::from_secs(600 + 2 * 3600 + 1), run).await;
+        let offsets = sent
+            .borrow()
+            .iter()
+            .map(|(count, at)| (*count, (*at - start).as_secs()))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [(1, 600), (2, 4200), (3, 7800)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stops_after_max() {
+        let reminders: Reminders = serde_json::from_str(r#"{ "waits": ["10m"], "max": 2 }"#).unwrap();
+        let sent = std::cell::Cell::new(0);
+        let run = reminders.run(|count| {
+            sent.set(count);
+            async {}
+        });
+        let _ = tokio::time::timeout(Duration::from_secs(10 * 3600), run).await;
+        assert_eq!(sent.get(), 2);
+    }
+
+    #[test]
+    fn test_postponed_outside_working_hours() {
+        let rotation: crate::config::Rotation = serde_json::from_value(serde_json::json!({
+            "time_zone": "Europe/Berlin",
+            "members": [{ "user_id": "alice" }],
+            "start": "2026-01-05T09:00:00",
+            "working_hours": { "start": "09:00:00", "end": "17:00:00" }
+        }))
+        .unwrap();
+        let reminders = Reminders::default().during(&rotation);
+        let utc = |time: &str| DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc);
+        // Wednesday 14:00 CEST is sent right away, Wednesday 23:00 waits for Thursday 09:00
+        assert_eq!(reminders.postponed(utc("2026-10-14T12:00:00Z")), Duration::ZERO);
+        assert_eq!(reminders.postponed(utc("2026-10-14T21:00:00Z")), Duration::from_secs(10 * 3600));
+        assert_eq!(Reminders::default().postponed(utc("2026-10-14T21:00:00Z")), Duration::ZERO);
+    }
+}
@@ -0,0 +1,268 @@
+//! Turns the agent's Markdown into something each chat service can post
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Questions needing more messages than this are posted as a file instead
+const MAX_MESSAGES: usize = 4;
+const FENCE: &str = "```";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Discord,
+    Slack,
+}
+
+impl Dialect {
+    /// Longest text a single message may carry
+    fn limit(self) -> usize {
+        match self {
+            Self::Discord => 2000,
+            // chat.postMessage accepts more, but Slack truncates messages past 4000 characters
+            Self::Slack => 4000,
+        }
+    }
+
+    fn convert_line(self, line: &str) -> String {
+        match self {
+            Self::Discord => discord_line(line),
+            Self::Slack => slack_line(line),
+        }
+    }
+
+    fn convert_code_line(self, line: &str) -> String {
+        match self {
+            Self::Discord => line.to_string(),
+            Self::Slack => slack_escape(line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rendered {
+    /// The question fits in a few messages, posted in order
+    Messages(Vec<String>),
+    /// The question is too long to post; a preview is posted and the full
+    /// Markdown is uploaded as `question.md`
+    File { preview: String, markdown: String },
+}
+
+pub fn render(markdown: &str, dialect: Dialect) -> Rendered {
+    let converted = convert(markdown, dialect);
+    let messages = split(&converted, dialect.limit());
+    if messages.len() <= MAX_MESSAGES {
+        return Rendered::Messages(messages);
+    }
+    let note = "\n… (the full question is attached as `question.md`)";
+    let preview = split(&converted, dialect.limit() - note.chars().count())
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    Rendered::File {
+        preview: format!("{preview}{note}"),
+        markdown: markdown.to_string(),
+    }
+}
+
+/// Converts Markdown line by line, leaving fenced code untouched apart from
+/// the escaping the target requires.
+pub fn convert(markdown: &str, dialect: Dialect) -> String {
+    let mut in_fence = false;
+    markdown
+        .lines()
+        .map(|line| {
+            if line.trim_start().starts_with(FENCE) {
+                in_fence = !in_fence;
+                // Slack shows the language tag as code, so only the fence is kept
+                return match dialect {
+                    Dialect::Discord => line.to_string(),
+                    Dialect::Slack => FENCE.to_string(),
+                };
+            }
+            if in_fence {
+                dialect.convert_code_line(line)
+            } else {
+                // Inline code spans sit at odd indices and are kept verbatim
+                line.split('`')
+                    .enumerate()
+                    .map(|(i, part)| {
+                        if i % 2 == 1 {
+                            dialect.convert_code_line(part)
+                        } else {
+                            dialect.convert_line(part)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("`")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+static DEEP_HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^#{4,6}\s+(.*)$").unwrap());
+static HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^#{1,6}\s+(.*)$").unwrap());
+static LIST_ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\s*)[-*+]\s+").unwrap());
+static BOLD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\*\*(\S(?:.*?\S)?)\*\*|__(\S(?:.*?\S)?)__").unwrap());
+static ITALIC: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\*(\S(?:[^*]*?\S)?)\*").unwrap());
+static STRIKE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"~~(.+?)~~").unwrap());
+static LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!?\[([^\]]*)\]\(([^)\s]+)\)").unwrap());
+
+/// Discord understands most Markdown but only three heading levels
+fn discord_line(line: &str) -> String {
+    DEEP_HEADING.replace(line, "**$1**").into_owned()
+}
+
+/// Escaping `&`, `<` and `>` also defuses `<!channel>`, `<!here>`, `<@U…>` and
+/// `<!subteam^…>` mentions, so agent text can't ping anyone on its own
+pub fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn slack_line(line: &str) -> String {
+    let line = slack_escape(line);
+    // Bold becomes a placeholder first so the italic pass doesn't pick it up
+    let line = HEADING.replace(&line, "\u{1}$1\u{1}");
+    let line = LIST_ITEM.replace(&line, "$1• ");
+    let line = BOLD.replace_all(&line, "\u{1}$1$2\u{1}");
+    let line = ITALIC.replace_all(&line, "_${1}_");
+    let line = STRIKE.replace_all(&line, "~$1~");
+    let line = LINK.replace_all(&line, "<$2|$1>");
+    line.replace('\u{1}', "*")
+}
+
+/// Splits text into chunks of at most `limit` characters, preferring line
+/// boundaries. A code block cut in two is closed at the end of one chunk and
+/// reopened at the start of the next so both render as code.
+pub fn split(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    let mut open_fence: Option<&str> = None;
+
+    for line in text.split('\n') {
+        let is_fence = line.trim_start().starts_with(FENCE);
+        let closing_room = if open_fence.is_some() || is_fence {
+            FENCE.len() + 1
+        } else {
+            0
+        };
+        let reopening_room = open_fence.map_or(0, |fence| fence.chars().count() + 1);
+        let max_piece = limit.saturating_sub(closing_room + reopening_room).max(1);
+
+        for piece in wrap(line, max_piece) {
+            let piece_len = piece.chars().count();
+            if current_len > 0 && current_len + 1 + piece_len + closing_room > limit {
+                if open_fence.is_some() {
+                    current.push('\n');
+                    current.push_str(FENCE);
+                }
+                chunks.push(std::mem::take(&mut current));
+                current_len = 0;
+                if let Some(fence) = open_fence {
+                    current.push_str(fence);
+                    current_len = fence.chars().count();
+                }
+            }
+            if current_len > 0 {
+                current.push('\n');
+                current_len += 1;
+            }
+            current.push_str(piece);
+            current_len += piece_len;
+        }
+
+        if is_fence {
+            open_fence = match open_fence {
+                Some(_) => None,
+                None => Some(line.trim_start()),
+            };
+        }
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Hard-wraps a single line that is longer than `max` characters
+fn wrap(line: &str, max: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = line;
+    while rest.chars().count() > max {
+        let (at, _) = rest.char_indices().nth(max).unwrap();
+        pieces.push(&rest[..at]);
+        rest = &rest[at..];
+    }
+    pieces.push(rest);
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slack_conversion() {
+        assert_eq!(
+            convert("## Plan\n- **bold** and *italic* ~~gone~~", Dialect::Slack),
+            "*Plan*\n• *bold* and _italic_ ~gone~"
+        );
+        assert_eq!(
+            convert("See [the docs](https://example.com/?a=1&b=2)", Dialect::Slack),
+            "See <https://example.com/?a=1&amp;b=2|the docs>"
+        );
+        assert_eq!(
+            convert("Run `a **b** <c>`\n```rust\nlet x = 1 < 2;\n```", Dialect::Slack),
+            "Run `a **b** &lt;c&gt;`\n```\nlet x = 1 &lt; 2;\n```"
+        );
+    }
+
+    #[test]
+    fn test_slack_mentions_are_neutralized() {
+        let converted = convert("Hey <!channel> <!here|here> <@U123> <!subteam^S1>\n`<!everyone>`", Dialect::Slack);
+        assert!(!converted.contains('<') && !converted.contains('>'), "{converted}");
+    }
+
+    #[test]
+    fn test_discord_conversion() {
+        assert_eq!(convert("#### Details", Dialect::Discord), "**Details**");
+        assert_eq!(convert("## Plan", Dialect::Discord), "## Plan");
+    }
+
+    #[test]
+    fn test_split_respects_limit() {
+        let text = "word ".repeat(1000);
+        let chunks = split(&text, 100);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 100));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn test_split_keeps_code_fences_balanced() {
+        let code = (0..60).map(|i| format!("let line_{i} = {i};")).collect::<Vec<_>>().join("\n");
+        let text = format!("Before\n```rust\n{code}\n```\nAfter");
+        let chunks = split(&text, 200);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 200, "{chunk}");
+            assert_eq!(chunk.matches(FENCE).count() % 2, 0, "{chunk}");
+        }
+        assert!(chunks[1].starts_with("```rust\n"));
+    }
+
+    #[test]
+    fn test_render_falls_back_to_file() {
+        let markdown = "line\n".repeat(5000);
+        let Rendered::File { preview, markdown: full } = render(&markdown, Dialect::Discord) else {
+            panic!("long questions should be attached as a file");
+        };
+        assert!(preview.chars().count() <= 2000);
+        assert_eq!(full, markdown);
+    }
+}
@@ -0,0 +1,399 @@
+//! Code review of a unified diff, with comments keyed to file and line
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use rust_mcp_sdk::schema::{CallToolResult, TextContent};
+
+use crate::approval::parse_vote;
+
+/// `src/main.rs:42: comment` or `src/main.rs:40-45 comment`, optionally in backticks
+static LOCATED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*`?([^\s:`]+):(\d+)(?:-(\d+))?`?\s*[:\-–]?\s*(.*)$").unwrap());
+/// `@@ -12,7 +12,9 @@`, where the counts default to 1
+static HUNK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").unwrap());
+
+/// A file touched by the diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedFile {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// One line of a diff as shown to the reviewer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    /// `diff --git`, `---`, `+++` and other lines between files
+    Header(&'a str),
+    Hunk(&'a str),
+    Added { new: u32, text: &'a str },
+    Removed { old: u32, text: &'a str },
+    Context { old: u32, new: u32, text: &'a str },
+}
+
+/// Splits a unified diff into its lines, numbering them on each side. Hunk
+/// line counts are followed, so removed lines that look like `--- ` headers
+/// are read correctly.
+pub fn diff_lines(diff: &str) -> Vec<DiffLine<'_>> {
+    let (mut old, mut new) = (0, 0);
+    let (mut old_left, mut new_left) = (0, 0);
+    diff.lines()
+        .map(|line| {
+            if old_left == 0 && new_left == 0 {
+                let Some(hunk) = HUNK.captures(line) else {
+                    return DiffLine::Header(line);
+                };
+                let number = |i: usize, default: u32| hunk.get(i).map_or(default, |m| m.as_str().parse().unwrap_or(0));
+                (old, old_left, new, new_left) = (number(1, 0), number(2, 1), number(3, 0), number(4, 1));
+                return DiffLine::Hunk(line);
+            }
+            match line.chars().next() {
+                Some('+') if new_left > 0 => {
+                    new_left -= 1;
+                    new += 1;
+                    DiffLine::Added { new: new - 1, text: &line[1..] }
+                }
+                Some('-') if old_left > 0 => {
+                    old_left -= 1;
+                    old += 1;
+                    DiffLine::Removed { old: old - 1, text: &line[1..] }
+                }
+                // `\ No newline at end of file`, or a side of a hunk that
+                // has more lines than its header counted
+                Some('\\' | '+' | '-') => DiffLine::Header(line),
+                _ => {
+                    old_left = old_left.saturating_sub(1);
+                    new_left = new_left.saturating_sub(1);
+                    old += 1;
+                    new += 1;
+                    DiffLine::Context {
+                        old: old - 1,
+                        new: new - 1,
+                        text: line.get(1..).unwrap_or_default(),
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// The files a diff changes, in order, with their added and removed line counts
+pub fn changed_files(diff: &str) -> Vec<ChangedFile> {
+    let mut files: Vec<ChangedFile> = Vec::new();
+    let mut old_path = None;
+    for line in diff_lines(diff) {
+        match line {
+            DiffLine::Header(header) => {
+                if let Some(path) = header.strip_prefix("--- ") {
+                    old_path = Some(header_path(path));
+                } else if let Some(path) = header.strip_prefix("+++ ") {
+                    // Deleted files are only named on the `---` line
+                    let path = match header_path(path) {
+                        path if path == "/dev/null" => old_path.take().unwrap_or(path),
+                        path => path,
+                    };
+                    files.push(ChangedFile {
+                        path,
+                        added: 0,
+                        removed: 0,
+                    });
+                }
+            }
+            DiffLine::Added { .. } => {
+                if let Some(file) = files.last_mut() {
+                    file.added += 1;
+                }
+            }
+            DiffLine::Removed { .. } => {
+                if let Some(file) = files.last_mut() {
+                    file.removed += 1;
+                }
+            }
+            DiffLine::Hunk(_) | DiffLine::Context { .. } => {}
+        }
+    }
+    files
+}
+
+/// `b/src/main.rs\t2026-10-19 10:00` → `src/main.rs`
+fn header_path(path: &str) -> String {
+    let path = path.split('\t').next().unwrap_or(path).trim();
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// The message asking for the review, listing the changed files
+pub fn message(title: Option<&str>, description: Option<&str>, files: &[ChangedFile], web_view: Option<&str>) -> String {
+    let mut message = format!("Please review {}.", title.unwrap_or("these changes"));
+    if let Some(description) = description {
+        message.push_str(&format!("\n{description}"));
+    }
+    for file in files {
+        message.push_str(&format!("\n`{}` +{} −{}", file.path, file.added, file.removed));
+    }
+    if let Some(url) = web_view {
+        message.push_str(&format!("\nRendered diff: {url}"));
+    }
+    message.push_str(
+        "\n_Reply `approve` or `request changes`, followed by any comments on lines like \
+         `src/main.rs:42: rename this`._",
+    );
+    message
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Approved,
+    ChangesRequested,
+    /// Comments without a verdict
+    Commented,
+}
+
+impl Decision {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Approved => "approved",
+            Self::ChangesRequested => "changes_requested",
+            Self::Commented => "commented",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewComment {
+    pub path: String,
+    pub line: u32,
+    /// Last line of a comment on a range, e.g. `src/main.rs:40-45`
+    pub end_line: Option<u32>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Review {
+    pub decision: Decision,
+    /// Everything the reviewer wrote that isn't about a specific line
+    pub body: String,
+    pub comments: Vec<ReviewComment>,
+}
+
+impl Review {
+    /// Reads a reply to a review request, or explains what couldn't be matched to the diff
+    pub fn parse(reply: &str, files: &[ChangedFile]) -> Result<Self, String> {
+        let reply = reply.trim();
+        let first_line = reply.lines().next().unwrap_or_default();
+        let (decision, verdict_len) = verdict(first_line);
+        // `approve, nice work` leaves `nice work`
+        let rest = match verdict_len {
+            0 => reply,
+            _ => reply[verdict_len..].trim_start_matches([',', ':', '-', '.', '!', ' ']),
+        };
+        let mut body = Vec::new();
+        let mut comments: Vec<ReviewComment> = Vec::new();
+        let mut continues_comment = false;
+        for line in rest.lines() {
+            let located = LOCATED.captures(line).filter(|captures| {
+                // `10:30 works for me` is not about a file
+                let path = &captures[1];
+                path.contains(['.', '/']) || files.iter().any(|file| file.path == path)
+            });
+            match located {
+                Some(captures) => {
+                    let path = find_file(&captures[1], files)?;
+                    let line_number = |i: usize| captures.get(i).and_then(|m| m.as_str().parse().ok());
+                    comments.push(ReviewComment {
+                        path,
+                        line: line_number(2).unwrap_or(0),
+                        end_line: line_number(3),
+                        body: captures[4].trim().to_string(),
+                    });
+                    continues_comment = true;
+                }
+                // Lines after a comment continue it, until a blank line
+                None if line.trim().is_empty() => {
+                    continues_comment = false;
+                    body.push(line);
+                }
+                None => match comments.last_mut() {
+                    Some(comment) if continues_comment => {
+                        comment.body.push('\n');
+                        comment.body.push_str(line.trim());
+                    }
+                    _ => body.push(line),
+                },
+            }
+        }
+        let body = body.join("\n").trim().to_string();
+        Ok(Self {
+            decision,
+            body,
+            comments,
+        })
+    }
+
+    pub fn into_tool_result(self) -> CallToolResult {
+        let comments = self
+            .comments
+            .into_iter()
+            .map(|comment| {
+                let mut item = serde_json::json!({
+                    "path": comment.path,
+                    "line": comment.line,
+                    "body": comment.body,
+                });
+                if let Some(end_line) = comment.end_line {
+                    item["end_line"] = end_line.into();
+                }
+                item
+            })
+            .collect::<Vec<_>>();
+        let mut review = serde_json::json!({ "decision": self.decision.name(), "comments": comments });
+        if !self.body.is_empty() {
+            review["body"] = self.body.into();
+        }
+        let text = serde_json::to_string_pretty(&review).unwrap_or_default();
+        let mut meta = serde_json::Map::new();
+        meta.insert("review".to_string(), review);
+        CallToolResult {
+            content: vec![TextContent::new(text, None).into()],
+            is_error: None,
+            meta: Some(meta),
+        }
+    }
+}
+
+/// The decision at the start of the reply and how many bytes it takes up
+fn verdict(first_line: &str) -> (Decision, usize) {
+    let lower = first_line.to_lowercase();
+    for phrase in ["request changes", "requesting changes", "changes requested"] {
+        if lower.starts_with(phrase) {
+            return (Decision::ChangesRequested, phrase.len());
+        }
+    }
+    let word = first_line
+        .split([' ', ',', ':', '.', '!'])
+        .next()
+        .unwrap_or_default();
+    let decision = match word {
+        "✅" => Some(true),
+        "❌" => Some(false),
+        word if word.eq_ignore_ascii_case("changes") => Some(false),
+        word => parse_vote(word),
+    };
+    match decision {
+        Some(true) => (Decision::Approved, word.len()),
+        Some(false) => (Decision::ChangesRequested, word.len()),
+        None => (Decision::Commented, 0),
+    }
+}
+
+/// The changed file a comment refers to, allowing the path to be shortened
+/// to its last components, e.g. `main.rs` for `src/main.rs`
+fn find_file(path: &str, files: &[ChangedFile]) -> Result<String, String> {
+    if let Some(file) = files.iter().find(|file| file.path == path) {
+        return Ok(file.path.clone());
+    }
+    let suffix = format!("/{path}");
+    let matching = files.iter().filter(|file| file.path.ends_with(&suffix)).collect::<Vec<_>>();
+    match matching.as_slice() {
+        [file] => Ok(file.path.clone()),
+        [] => Err(format!(
+            "`{path}` isn't one of the changed files: {}.",
+            files.iter().map(|file| format!("`{}`", file.path)).collect::<Vec<_>>().join(", ")
+        )),
+        _ => Err(format!("`{path}` matches more than one changed file; give its full path.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,4 @@
+ fn main() {
+--- println!(\"old\");
++    println!(\"new\");
++    println!(\"more\");
+ }
+diff --git a/docs/old.md b/docs/old.md
+deleted file mode 100644
+--- a/docs/old.md
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+
+    #[test]
+    fn test_changed_files() {
+        assert_eq!(
+            changed_files(DIFF),
+            [
+                ChangedFile {
+                    path: "src/main.rs".to_string(),
+                    added: 2,
+                    removed: 1
+                },
+                ChangedFile {
+                    path: "docs/old.md".to_string(),
+                    added: 0,
+                    removed: 1
+                },
+            ]
+        );
+        let lines = diff_lines(DIFF);
+        assert_eq!(lines[7], DiffLine::Added { new: 3, text: "    println!(\"more\");" });
+        assert_eq!(lines[8], DiffLine::Context { old: 3, new: 4, text: "}" });
+    }
+
+    #[test]
+    fn test_under_counted_hunk() {
+        let diff = "--- a/notes.md\n+++ b/notes.md\n@@ -1,2 +1,1 @@\n-old\n+new\n+extra\n same\n";
+        let lines = diff_lines(diff);
+        assert_eq!(lines[3], DiffLine::Removed { old: 1, text: "old" });
+        assert_eq!(lines[4], DiffLine::Added { new: 1, text: "new" });
+        assert_eq!(lines[5], DiffLine::Header("+extra"));
+        assert_eq!(lines[6], DiffLine::Context { old: 2, new: 2, text: "same" });
+    }
+
+    #[test]
+    fn test_parses_review() {
+        let files = changed_files(DIFF);
+        let review = Review::parse(
+            "Request changes, nearly there\n`main.rs:2`: use eprintln\nstdout is the transport\n\nsrc/main.rs:2-3 - drop one of these",
+            &files,
+        )
+        .unwrap();
+        assert_eq!(review.decision, Decision::ChangesRequested);
+        assert_eq!(review.body, "nearly there");
+        assert_eq!(
+            review.comments,
+            [
+                ReviewComment {
+                    path: "src/main.rs".to_string(),
+                    line: 2,
+                    end_line: None,
+                    body: "use eprintln\nstdout is the transport".to_string()
+                },
+                ReviewComment {
+                    path: "src/main.rs".to_string(),
+                    line: 2,
+                    end_line: Some(3),
+                    body: "drop one of these".to_string()
+                },
+            ]
+        );
+
+        let review = Review::parse("LGTM", &files).unwrap();
+        assert_eq!((review.decision, review.body.as_str()), (Decision::Approved, ""));
+        let review = Review::parse("Can we ship this at 10:30?", &files).unwrap();
+        assert_eq!(review.decision, Decision::Commented);
+        assert!(review.comments.is_empty());
+        assert!(Review::parse("lib.rs:3: what about this?", &files).is_err());
+    }
+}
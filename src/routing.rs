@@ -0,0 +1,132 @@
+//! Sends each question to the human responsible for its audience
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::approval::Verdict;
+use crate::config::ApprovalPolicy;
+use crate::tools::{Answer, Human, Question};
+
+pub struct Router {
+    default: Arc<dyn Human>,
+    routes: HashMap<String, Arc<dyn Human>>,
+}
+
+impl Router {
+    pub fn new(default: Arc<dyn Human>) -> Self {
+        Self {
+            default,
+            routes: HashMap::new(),
+        }
+    }
+
+    pub fn with_route(mut self, audience: impl Into<String>, human: Arc<dyn Human>) -> Self {
+        self.routes.insert(audience.into(), human);
+        self
+    }
+
+    /// The audiences the agent can address, sorted for stable instructions
+    pub fn audiences(&self) -> Vec<&str> {
+        let mut audiences = self.routes.keys().map(String::as_str).collect::<Vec<_>>();
+        audiences.sort_unstable();
+        audiences
+    }
+
+    fn human_for(&self, audience: Option<&str>) -> anyhow::Result<&dyn Human> {
+        let Some(audience) = audience else {
+            return Ok(self.default.as_ref());
+        };
+        match self.routes.get(audience) {
+            Some(human) => Ok(human.as_ref()),
+            None if self.routes.is_empty() => {
+                anyhow::bail!("No audiences are configured; ask again without `audience`")
+            }
+            None => anyhow::bail!(
+                "Unknown audience `{audience}`. Known audiences: {}",
+                self.audiences().join(", ")
+            ),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Human for Router {
+    async fn ask(&self, question: &Question) -> anyhow::Result<Answer> {
+        self.human_for(question.audience.as_deref())?.ask(question).await
+    }
+
+    /// Every route keeps its own threads, and the next question may go to any of them
+    async fn start_new_thread(&self, title: Option<&str>, topic: Option<&str>) -> anyhow::Result<()> {
+        self.default.start_new_thread(title, topic).await?;
+        for human in self.routes.values() {
+            human.start_new_thread(title, topic).await?;
+        }
+        Ok(())
+    }
+
+    /// Votes are collected where the audience's questions go, so the approvers
+    /// named in the policy have to be users of that platform
+    async fn collect_votes(&self, question: &Question, policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+        self.human_for(question.audience.as_deref())?.collect_votes(question, policy).await
+    }
+
+    async fn next_reply(&self, question: &Question) -> anyhow::Result<Answer> {
+        self.human_for(question.audience.as_deref())?.next_reply(question).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approval::{Decision, Vote};
+
+    /// Answers every question, and casts every vote, with its own name
+    struct Named(&'static str);
+
+    #[async_trait::async_trait]
+    impl Human for Named {
+        async fn ask(&self, _question: &Question) -> anyhow::Result<Answer> {
+            Ok(Answer::from_reply(self.0, None))
+        }
+
+        async fn start_new_thread(&self, _title: Option<&str>, _topic: Option<&str>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn collect_votes(&self, _question: &Question, _policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+            Ok(Verdict {
+                decision: Decision::Approved,
+                votes: vec![Vote {
+                    voter_id: self.0.to_string(),
+                    voter_name: None,
+                    approve: true,
+                }],
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_audience() {
+        let router = Router::new(Arc::new(Named("default")))
+            .with_route("infra", Arc::new(Named("on-call")))
+            .with_route("legal", Arc::new(Named("counsel")));
+        let ask = |audience: Option<&str>| Question {
+            text: "Who are you?".to_string(),
+            audience: audience.map(str::to_string),
+            ..Default::default()
+        };
+        assert_eq!(router.ask(&ask(None)).await.unwrap().text, "default");
+        assert_eq!(router.ask(&ask(Some("infra"))).await.unwrap().text, "on-call");
+        let error = router.ask(&ask(Some("design"))).await.unwrap_err().to_string();
+        assert_eq!(error, "Unknown audience `design`. Known audiences: infra, legal");
+
+        let policy = ApprovalPolicy {
+            tools: Vec::new(),
+            approvers: vec!["on-call".to_string()],
+            quorum: 1,
+            veto: true,
+        };
+        let verdict = router.collect_votes(&ask(Some("infra")), &policy).await.unwrap();
+        assert_eq!(verdict.votes[0].voter_id, "on-call");
+    }
+}
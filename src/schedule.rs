@@ -0,0 +1,227 @@
+//! On-call rotations deciding who is asked, and whether anyone can be asked right now
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+
+use crate::approval::Verdict;
+use crate::config::{ApprovalPolicy, Rotation};
+use crate::tools::{Answer, Human, Question};
+
+/// How far ahead to look for the next working day before giving up
+const MAX_DAYS_AHEAD: i64 = 366;
+
+impl Rotation {
+    /// Index of the member whose shift includes `now`
+    pub fn on_call_at(&self, now: DateTime<Utc>) -> usize {
+        let local = now.with_timezone(&self.time_zone).naive_local();
+        let days = (local - self.start).num_seconds().div_euclid(24 * 60 * 60);
+        let shift = days.div_euclid(i64::from(self.shift_days.max(1)));
+        shift.rem_euclid(self.members.len().max(1) as i64) as usize
+    }
+
+    /// Whether `now` is within working hours and not on a holiday
+    pub fn is_available(&self, now: DateTime<Utc>) -> bool {
+        let local = now.with_timezone(&self.time_zone).naive_local();
+        let working_day = match &self.working_hours {
+            None => Some(local.date()),
+            Some(hours) => hours.working_day(local),
+        };
+        working_day.is_some_and(|date| !self.holidays.contains(&date))
+    }
+
+    /// The first moment from `now` on when someone can be asked, if any within a year
+    pub fn next_available(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.is_available(now) {
+            return Some(now);
+        }
+        let today = now.with_timezone(&self.time_zone).date_naive();
+        (0..=MAX_DAYS_AHEAD)
+            .map(|offset| today + Duration::days(offset))
+            .filter(|date| !self.holidays.contains(date))
+            .filter_map(|date| match &self.working_hours {
+                None => Some(date.and_hms_opt(0, 0, 0)?),
+                Some(hours) if hours.days.contains(&date.weekday()) => Some(date.and_time(hours.start)),
+                Some(_) => None,
+            })
+            .filter_map(|start| self.time_zone.from_local_datetime(&start).earliest())
+            .map(|start| start.with_timezone(&Utc))
+            .find(|start| *start > now)
+    }
+}
+
+/// Asks whoever is on call in a rotation when the question comes in
+pub struct OnCall {
+    name: String,
+    rotation: Rotation,
+    /// One backend per rotation member, in the rotation's order
+    members: Vec<Arc<dyn Human>>,
+}
+
+impl OnCall {
+    pub fn new(name: impl Into<String>, rotation: Rotation, members: Vec<Arc<dyn Human>>) -> anyhow::Result<Self> {
+        let name = name.into();
+        if members.is_empty() || members.len() != rotation.members.len() {
+            anyhow::bail!("Rotation `{name}` needs one backend per member");
+        }
+        Ok(Self { name, rotation, members })
+    }
+
+    /// When a question coming in at `now` can be asked: right away, once
+    /// someone is next available, or never with an explanation for the agent
+    fn ask_at(&self, now: DateTime<Utc>) -> anyhow::Result<DateTime<Utc>> {
+        let Some(from) = self.rotation.next_available(now) else {
+            anyhow::bail!("No one on the `{}` rotation is available in the coming year", self.name);
+        };
+        if self.rotation.report_unavailable && from > now {
+            anyhow::bail!(
+                "No one on the `{}` rotation is available until {} ({}). Continue without an answer if you can, or ask again then.",
+                self.name,
+                from.with_timezone(&self.rotation.time_zone).format("%a %Y-%m-%d %H:%M %Z"),
+                from.to_rfc3339()
+            );
+        }
+        Ok(from)
+    }
+
+    /// The member on call once someone can be asked, holding the question until then
+    async fn member(&self) -> anyhow::Result<&dyn Human> {
+        let now = Utc::now();
+        let at = self.ask_at(now)?;
+        if let Ok(wait) = (at - now).to_std() {
+            if !wait.is_zero() {
+                eprintln!("⏳ Holding a question for the `{}` rotation until {}", self.name, at.to_rfc3339());
+                tokio::time::sleep(wait).await;
+            }
+        }
+        Ok(self.members[self.rotation.on_call_at(at)].as_ref())
+    }
+}
+
+#[async_trait::async_trait]
+impl Human for OnCall {
+    async fn ask(&self, question: &Question) -> anyhow::Result<Answer> {
+        self.member().await?.ask(question).await
+    }
+
+    async fn start_new_thread(&self, title: Option<&str>, topic: Option<&str>) -> anyhow::Result<()> {
+        for member in &self.members {
+            member.start_new_thread(title, topic).await?;
+        }
+        Ok(())
+    }
+
+    async fn collect_votes(&self, question: &Question, policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+        self.member().await?.collect_votes(question, policy).await
+    }
+
+    /// Whoever was asked replies, and the rotation may have moved on since
+    async fn next_reply(&self, question: &Question) -> anyhow::Result<Answer> {
+        let replies = self.members.iter().map(|member| member.next_reply(question));
+        futures::future::select_all(replies).await.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotation() -> Rotation {
+        serde_json::from_value(serde_json::json!({
+            "time_zone": "Europe/Berlin",
+            "members": [{ "user_id": "alice" }, { "user_id": "bob" }],
+            "start": "2026-01-05T09:00:00",
+            "working_hours": { "start": "09:00:00", "end": "17:00:00" },
+            "holidays": ["2026-12-25"],
+            "report_unavailable": true
+        }))
+        .unwrap()
+    }
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_weekly_shifts() {
+        let rotation = rotation();
+        // Monday 10:00 in Berlin, in the first week and the second
+        assert_eq!(rotation.on_call_at(utc("2026-01-05T09:00:00Z")), 0);
+        assert_eq!(rotation.on_call_at(utc("2026-01-12T09:00:00Z")), 1);
+        assert_eq!(rotation.on_call_at(utc("2026-01-19T09:00:00Z")), 0);
+        // The handover happens at 09:00 local time, not midnight UTC
+        assert_eq!(rotation.on_call_at(utc("2026-01-12T07:00:00Z")), 0);
+    }
+
+    #[test]
+    fn test_working_hours_and_holidays() {
+        let rotation = rotation();
+        // Wednesday 14:00 CEST
+        let afternoon = utc("2026-10-14T12:00:00Z");
+        assert!(rotation.is_available(afternoon));
+        assert_eq!(rotation.next_available(afternoon), Some(afternoon));
+        // Friday 22:00 CEST waits for Monday 09:00
+        assert_eq!(
+            rotation.next_available(utc("2026-10-16T20:00:00Z")),
+            Some(utc("2026-10-19T07:00:00Z"))
+        );
+        // Christmas Day is a Friday, so the next chance is Monday 09:00 CET
+        assert!(!rotation.is_available(utc("2026-12-25T10:00:00Z")));
+        assert_eq!(
+            rotation.next_available(utc("2026-12-25T10:00:00Z")),
+            Some(utc("2026-12-28T08:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_night_shift_past_midnight() {
+        let mut rotation = rotation();
+        rotation.working_hours = serde_json::from_value(serde_json::json!({ "start": "22:00:00", "end": "06:00:00" })).unwrap();
+        // Wednesday 23:00 and Thursday 03:00 CEST are in Wednesday's shift, Thursday 12:00 isn't
+        assert!(rotation.is_available(utc("2026-10-14T21:00:00Z")));
+        assert!(rotation.is_available(utc("2026-10-15T01:00:00Z")));
+        assert!(!rotation.is_available(utc("2026-10-15T10:00:00Z")));
+        // Saturday 03:00 is still Friday's shift, Sunday 03:00 belongs to Saturday's, which there isn't
+        assert!(rotation.is_available(utc("2026-10-17T01:00:00Z")));
+        assert!(!rotation.is_available(utc("2026-10-18T01:00:00Z")));
+        assert_eq!(
+            rotation.next_available(utc("2026-10-18T01:00:00Z")),
+            Some(utc("2026-10-19T20:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_out_of_hours_questions_wait_unless_reported() {
+        let members: Vec<Arc<dyn Human>> = vec![Arc::new(Nobody), Arc::new(Nobody)];
+        let mut rotation = rotation();
+        rotation.report_unavailable = false;
+        let on_call = OnCall::new("infra", rotation.clone(), members.clone()).unwrap();
+        // Friday 22:00 CEST is held until Monday 09:00
+        let friday_night = utc("2026-10-16T20:00:00Z");
+        assert_eq!(on_call.ask_at(friday_night).unwrap(), utc("2026-10-19T07:00:00Z"));
+        let afternoon = utc("2026-10-14T12:00:00Z");
+        assert_eq!(on_call.ask_at(afternoon).unwrap(), afternoon);
+
+        rotation.report_unavailable = true;
+        let on_call = OnCall::new("infra", rotation, members).unwrap();
+        let error = on_call.ask_at(friday_night).unwrap_err().to_string();
+        assert!(error.starts_with("No one on the `infra` rotation is available until Mon 2026-10-19 09:00 CEST"), "{error}");
+    }
+
+    struct Nobody;
+
+    #[async_trait::async_trait]
+    impl Human for Nobody {
+        async fn ask(&self, _question: &Question) -> anyhow::Result<Answer> {
+            anyhow::bail!("not used")
+        }
+
+        async fn start_new_thread(&self, _title: Option<&str>, _topic: Option<&str>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn collect_votes(&self, _question: &Question, _policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+            anyhow::bail!("not used")
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Answers from a file of rules instead of a person, so agents can be tested
+//! against reproducible replies without a chat service
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::approval::{parse_vote, Ballot, Decision, Verdict, Vote};
+use crate::audit::AuditLog;
+use crate::config::ApprovalPolicy;
+use crate::reminders::parse_duration;
+use crate::tools::{Answer, Human, Question};
+
+/// Shown as the responder of scripted answers
+const RESPONDER: &str = "script";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Script {
+    /// Tried in order; the first matching rule with uses left answers
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+    /// Reply to questions no rule matches; they fail when omitted
+    #[serde(default)]
+    fallback: Option<String>,
+    /// File unmatched questions are appended to as JSON lines; stderr when omitted
+    #[serde(default)]
+    unmatched_log: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    /// The whole question, ignoring surrounding whitespace
+    #[serde(default)]
+    exact: Option<String>,
+    /// Regular expression found anywhere in the question
+    #[serde(default)]
+    regex: Option<String>,
+    /// Text found anywhere in the question, ignoring case
+    #[serde(default)]
+    contains: Option<String>,
+    /// The reply, which may use `!skip`, `!idk`, `!later` and `!answer` like a person
+    answer: String,
+    /// Wait before answering, e.g. `2s`
+    #[serde(default)]
+    delay: Option<String>,
+    /// How many questions the rule answers before it is passed over
+    #[serde(default)]
+    times: Option<u32>,
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Exact(String),
+    Regex(Regex),
+    Contains(String),
+}
+
+impl Matcher {
+    fn matches(&self, text: &str) -> bool {
+        match self {
+            Self::Exact(exact) => text.trim() == exact.trim(),
+            Self::Regex(regex) => regex.is_match(text),
+            Self::Contains(contains) => text.to_lowercase().contains(contains),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    matcher: Matcher,
+    answer: String,
+    delay: Option<Duration>,
+    times: Option<u32>,
+}
+
+impl TryFrom<RuleConfig> for Rule {
+    type Error = String;
+
+    fn try_from(rule: RuleConfig) -> Result<Self, String> {
+        let matcher = match (rule.exact, rule.regex, rule.contains) {
+            (Some(exact), None, None) => Matcher::Exact(exact),
+            (None, Some(regex), None) => {
+                Matcher::Regex(Regex::new(&regex).map_err(|e| format!("Invalid regex `{regex}`: {e}"))?)
+            }
+            (None, None, Some(contains)) => Matcher::Contains(contains.to_lowercase()),
+            _ => return Err("Give exactly one of `exact`, `regex` or `contains`".to_string()),
+        };
+        Ok(Self {
+            matcher,
+            answer: rule.answer,
+            delay: rule.delay.as_deref().map(parse_duration).transpose()?,
+            times: rule.times,
+        })
+    }
+}
+
+pub struct HumanInScript {
+    rules: Vec<Rule>,
+    /// Questions each rule has answered so far
+    used: Mutex<Vec<u32>>,
+    fallback: Option<String>,
+    unmatched: AuditLog,
+}
+
+impl HumanInScript {
+    /// Reads the rules from a JSON file, or YAML for any other extension
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read script {}: {e}", path.display()))?;
+        let script: Script = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_str(&text).map_err(anyhow::Error::from),
+            _ => serde_yaml::from_str(&text).map_err(anyhow::Error::from),
+        }
+        .map_err(|e| anyhow::anyhow!("Failed to parse script {}: {e}", path.display()))?;
+        Self::new(script)
+    }
+
+    fn new(script: Script) -> anyhow::Result<Self> {
+        let rules = script
+            .rules
+            .into_iter()
+            .enumerate()
+            .map(|(i, rule)| Rule::try_from(rule).map_err(|e| anyhow::anyhow!("Rule {}: {e}", i + 1)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            used: Mutex::new(vec![0; rules.len()]),
+            rules,
+            fallback: script.fallback,
+            unmatched: AuditLog::open(script.unmatched_log.as_deref())?,
+        })
+    }
+
+    /// The scripted reply to `question` and how long to wait before giving it
+    fn reply(&self, question: &Question) -> anyhow::Result<(String, Option<Duration>)> {
+        let rule = {
+            let mut used = self.used.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let found = self.rules.iter().zip(used.iter()).position(|(rule, used)| {
+                rule.times.is_none_or(|times| *used < times) && rule.matcher.matches(&question.text)
+            });
+            found.map(|i| {
+                used[i] += 1;
+                &self.rules[i]
+            })
+        };
+        if let Some(rule) = rule {
+            return Ok((rule.answer.clone(), rule.delay));
+        }
+        self.unmatched.record(
+            "unmatched_question",
+            serde_json::json!({
+                "question": question.text,
+                "topic": question.topic,
+                "audience": question.audience,
+                "fallback": self.fallback,
+            }),
+        );
+        match &self.fallback {
+            Some(fallback) => Ok((fallback.clone(), None)),
+            None => anyhow::bail!("No scripted answer matches the question: {}", question.text),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Human for HumanInScript {
+    async fn ask(&self, question: &Question) -> anyhow::Result<Answer> {
+        let (reply, delay) = self.reply(question)?;
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        Ok(Answer::from_reply(reply, Some(RESPONDER.to_string())))
+    }
+
+    async fn start_new_thread(&self, _title: Option<&str>, _topic: Option<&str>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Every approver votes the way the scripted reply does
+    async fn collect_votes(&self, question: &Question, policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+        let (reply, delay) = self.reply(question)?;
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        let approve = parse_vote(reply.trim())
+            .ok_or_else(|| anyhow::anyhow!("The scripted reply `{reply}` is not a vote"))?;
+        let mut ballot = Ballot::new(policy);
+        let votes = policy.approvers.iter().map(|approver| Vote {
+            voter_id: approver.clone(),
+            voter_name: None,
+            approve,
+        });
+        let verdict = votes.filter_map(|vote| ballot.cast(vote)).next();
+        Ok(verdict.unwrap_or(Verdict {
+            decision: Decision::Rejected,
+            votes: Vec::new(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::AnswerStatus;
+
+    fn script(yaml: &str) -> HumanInScript {
+        HumanInScript::new(serde_yaml::from_str(yaml).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_answers_from_rules() {
+        let human = script(
+            r#"
+rules:
+  - exact: "Deploy now?"
+    answer: "yes"
+  - regex: "(?i)which (region|zone)"
+    answer: "eu-west-1"
+    times: 1
+  - contains: "BUDGET"
+    answer: "!later 1d ask finance"
+fallback: "!idk"
+"#,
+        );
+        let ask = |text: &str| Question::new(text);
+        assert_eq!(human.ask(&ask(" Deploy now?\n")).await.unwrap().text, "yes");
+        let answer = human.ask(&ask("Which region should it run in?")).await.unwrap();
+        assert_eq!((answer.text.as_str(), answer.responder.as_deref()), ("eu-west-1", Some("script")));
+        // The regex rule is used up, so the fallback answers
+        assert_eq!(human.ask(&ask("Which zone?")).await.unwrap().status, AnswerStatus::Unknown);
+        let answer = human.ask(&ask("What is the budget?")).await.unwrap();
+        assert!(matches!(answer.status, AnswerStatus::Deferred { until: Some(_) }));
+        assert_eq!(answer.text, "ask finance");
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_without_fallback_fails() {
+        let human = script("rules:\n  - contains: deploy\n    answer: approve\n");
+        let error = human.ask(&Question::new("Which region?")).await.unwrap_err();
+        assert_eq!(error.to_string(), "No scripted answer matches the question: Which region?");
+
+        let policy = ApprovalPolicy {
+            tools: vec!["*".to_string()],
+            approvers: vec!["U1".to_string(), "U2".to_string()],
+            quorum: 2,
+            veto: true,
+        };
+        let verdict = human.collect_votes(&Question::new("Deploy to prod?"), &policy).await.unwrap();
+        assert_eq!((verdict.decision, verdict.votes.len()), (Decision::Approved, 2));
+
+        let invalid = serde_yaml::from_str("rules:\n  - exact: a\n    contains: b\n    answer: c\n").unwrap();
+        let error = HumanInScript::new(invalid).err().unwrap().to_string();
+        assert_eq!(error, "Rule 1: Give exactly one of `exact`, `regex` or `contains`");
+    }
+}
@@ -0,0 +1,87 @@
+//! Gives up on questions nobody answers in time, so the agent isn't blocked
+//! on a human who is away
+
+use std::time::Duration;
+
+use crate::approval::Verdict;
+use crate::config::ApprovalPolicy;
+use crate::tools::{Answer, Human, Question};
+
+/// Answers a question with `timed_out` once its timeout, or the configured
+/// default, passes without a reply. The question is withdrawn in chat.
+pub struct TimingOut<H> {
+    human: H,
+    default: Option<Duration>,
+}
+
+impl<H: Human> TimingOut<H> {
+    pub fn new(human: H, default: Option<Duration>) -> Self {
+        Self { human, default }
+    }
+}
+
+#[async_trait::async_trait]
+impl<H: Human> Human for TimingOut<H> {
+    async fn ask(&self, question: &Question) -> anyhow::Result<Answer> {
+        let Some(limit) = question.timeout.or(self.default) else {
+            return self.human.ask(question).await;
+        };
+        match tokio::time::timeout(limit, self.human.ask(question)).await {
+            Ok(answer) => answer,
+            Err(_) => {
+                eprintln!("⌛ No answer within {}s: {}", limit.as_secs(), question.text);
+                Ok(Answer::timed_out(limit))
+            }
+        }
+    }
+
+    async fn start_new_thread(&self, title: Option<&str>, topic: Option<&str>) -> anyhow::Result<()> {
+        self.human.start_new_thread(title, topic).await
+    }
+
+    async fn collect_votes(&self, question: &Question, policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+        self.human.collect_votes(question, policy).await
+    }
+
+    async fn next_reply(&self, question: &Question) -> anyhow::Result<Answer> {
+        self.human.next_reply(question).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::AnswerStatus;
+
+    struct Silent;
+
+    #[async_trait::async_trait]
+    impl Human for Silent {
+        async fn ask(&self, _question: &Question) -> anyhow::Result<Answer> {
+            std::future::pending().await
+        }
+
+        async fn start_new_thread(&self, _title: Option<&str>, _topic: Option<&str>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn collect_votes(&self, _question: &Question, _policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_question_timeout_overrides_default() {
+        let human = TimingOut::new(Silent, Some(Duration::from_secs(3600)));
+        let question = Question {
+            timeout: Some(Duration::from_secs(60)),
+            ..Question::new("Deploy now?")
+        };
+        let started = tokio::time::Instant::now();
+        let answer = human.ask(&question).await.unwrap();
+        assert_eq!(answer.status, AnswerStatus::TimedOut { after: Duration::from_secs(60) });
+        assert_eq!(started.elapsed(), Duration::from_secs(60));
+        let answer = human.ask(&Question::new("Deploy now?")).await.unwrap();
+        assert_eq!(answer.status, AnswerStatus::TimedOut { after: Duration::from_secs(3600) });
+    }
+}
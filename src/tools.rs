@@ -1,13 +1,361 @@
-use rust_mcp_sdk::schema::{schema_utils::CallToolError, CallToolResult};
+use rust_mcp_sdk::schema::{schema_utils::CallToolError, CallToolResult, TextContent};
 use rust_mcp_sdk::{
     macros::{mcp_tool, JsonSchema},
     tool_box,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::approval::Verdict;
+use crate::attachment::Attachment;
+use crate::batch::Batch;
+use crate::commands::WithdrawnOnDrop;
+use crate::reminders::parse_duration;
+use crate::config::ApprovalPolicy;
+use crate::conversation::Conversations;
+use crate::plan::Plan;
+use crate::reactions::{asks_yes_or_no, NUMBER_EMOJI};
+use crate::review::{changed_files, Review};
+use crate::review_web::ReviewPages;
+use crate::validation::{retry_text, ExpectedAnswer, Expectation};
+
 #[async_trait::async_trait]
 pub trait Human: Send + Sync + 'static {
-    async fn ask(&self, question: &str) -> anyhow::Result<String>;
+    async fn ask(&self, question: &Question) -> anyhow::Result<Answer>;
+
+    /// Makes the next question (for `topic`, when given) open a new thread
+    async fn start_new_thread(&self, title: Option<&str>, topic: Option<&str>) -> anyhow::Result<()>;
+
+    /// Puts `question` to the policy's approvers and collects their votes until
+    /// the policy decides it
+    async fn collect_votes(&self, question: &Question, policy: &ApprovalPolicy) -> anyhow::Result<Verdict>;
+
+    /// Waits for the human's next reply in `question.thread` without posting
+    /// anything. Backends without threads never hear one.
+    async fn next_reply(&self, _question: &Question) -> anyhow::Result<Answer> {
+        std::future::pending().await
+    }
+}
+
+/// Starts the reply that ends a conversation, as opposed to a question back
+pub const FINAL_ANSWER: &str = "!answer";
+
+/// Replies that can't be read before a tool stops asking again, so a reply
+/// that keeps coming back the same, e.g. from a script, can't loop forever
+const MAX_UNREADABLE_REPLIES: usize = 5;
+
+/// Counts the replies a tool couldn't read
+#[derive(Default)]
+struct Unreadable(usize);
+
+impl Unreadable {
+    /// Records a reply that couldn't be read because of `problem`, failing once there were too many
+    fn record(&mut self, problem: &str) -> anyhow::Result<()> {
+        self.0 += 1;
+        if self.0 >= MAX_UNREADABLE_REPLIES {
+            anyhow::bail!("Gave up after {} replies that couldn't be read. The last one: {problem}", self.0);
+        }
+        Ok(())
+    }
+}
+
+/// Snippets up to this size are shown inline as code blocks; larger ones are uploaded as files
+const INLINE_SNIPPET_MAX_LINES: usize = 15;
+const INLINE_SNIPPET_MAX_CHARS: usize = 600;
+
+/// A question for the human along with any context the agent attached to it
+#[derive(Debug, Clone, Default)]
+pub struct Question {
+    pub text: String,
+    pub attachments: Vec<QuestionAttachment>,
+    /// Label grouping related questions into the same thread
+    pub topic: Option<String>,
+    /// Choices the human can pick by reacting with a number
+    pub options: Vec<String>,
+    /// Routing key picking who is asked, e.g. `infra`
+    pub audience: Option<String>,
+    /// The kind of answer accepted; other replies are sent back to the human
+    pub expected: Option<Expectation>,
+    /// Whether the human may ask back before giving a final answer marked with `!answer`
+    pub conversation: bool,
+    /// Thread to post in instead of the usual one, to continue a conversation
+    pub thread: Option<String>,
+    /// Steps of a plan under review, which backends with forms offer as a checklist
+    pub steps: Vec<String>,
+    /// Whether `yes` or `no` answers it, so the quick answer reactions are offered
+    pub yes_or_no: bool,
+    /// How long to wait for an answer before giving up; the configured default when `None`
+    pub timeout: Option<std::time::Duration>,
+}
+
+impl Question {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// The question text followed by its numbered options and the attachments short enough to inline
+    pub fn message_body(&self) -> String {
+        let mut body = self.text.clone();
+        for (emoji, option) in NUMBER_EMOJI.iter().zip(&self.options) {
+            body.push_str(&format!("\n{emoji} {option}"));
+        }
+        for attachment in self.attachments.iter().filter(|a| a.is_short()) {
+            body.push_str(&format!("\n`{}`\n{}", attachment.filename, attachment.code_block()));
+        }
+        if let Some(expected) = &self.expected {
+            body.push_str(&format!("\n_Please answer with {}._", expected.describe()));
+        }
+        if self.conversation {
+            body.push_str(&format!(
+                "\n_Ask back if anything is unclear, and start your final answer with `{FINAL_ANSWER}`._"
+            ));
+        }
+        body
+    }
+
+    /// Attachments too long to inline, which backends upload as files
+    pub fn uploads(&self) -> impl Iterator<Item = &QuestionAttachment> {
+        self.attachments.iter().filter(|a| !a.is_short())
+    }
+}
+
+/// A file the agent attaches to a question, such as code, a diff or a log excerpt
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct QuestionAttachment {
+    /// File name shown to the human, e.g. `changes.diff`
+    pub filename: String,
+    /// The full text content of the file
+    pub content: String,
+    /// Language for syntax highlighting, e.g. `rust` or `diff`. Guessed from the file name when omitted.
+    pub language: Option<String>,
+}
+
+impl QuestionAttachment {
+    fn is_short(&self) -> bool {
+        self.content.lines().count() <= INLINE_SNIPPET_MAX_LINES
+            && self.content.chars().count() <= INLINE_SNIPPET_MAX_CHARS
+            && !self.content.contains("```")
+    }
+
+    pub fn language(&self) -> &str {
+        if let Some(language) = &self.language {
+            return language;
+        }
+        match self.filename.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("rs") => "rust",
+            Some("py") => "python",
+            Some("js") => "javascript",
+            Some("ts") => "typescript",
+            Some("sh") => "bash",
+            Some("yml") => "yaml",
+            Some("md") => "markdown",
+            Some("patch") => "diff",
+            Some(extension) => extension,
+            None => "",
+        }
+    }
+
+    fn code_block(&self) -> String {
+        format!("```{}\n{}\n```", self.language(), self.content.trim_end_matches('\n'))
+    }
+}
+
+/// What kind of reply the human gave
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnswerStatus {
+    Answered,
+    /// `!skip`: the human won't answer this question
+    Declined,
+    /// `!idk`: the human doesn't know the answer
+    Unknown,
+    /// `!later [wait]`: the human will answer later, from `until` when given
+    Deferred { until: Option<DateTime<Utc>> },
+    /// The human asked a question back, which the agent answers with `reply_to_human`
+    Clarification { conversation_id: u64 },
+    /// Nobody answered within the question's timeout
+    TimedOut { after: std::time::Duration },
+}
+
+impl AnswerStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Answered => "answered",
+            Self::Declined => "declined",
+            Self::Unknown => "unknown",
+            Self::Deferred { .. } => "deferred",
+            Self::Clarification { .. } => "clarification",
+            Self::TimedOut { .. } => "timed_out",
+        }
+    }
+}
+
+/// The human's reply to a question
+#[derive(Debug, Clone)]
+pub struct Answer {
+    pub status: AnswerStatus,
+    /// The reply, or for `!` commands the note after them
+    pub text: String,
+    /// Chat user ID of whoever replied, when known
+    pub responder: Option<String>,
+    pub answered_at: DateTime<Utc>,
+    pub attachments: Vec<Attachment>,
+    /// The thread the question was asked in, where a conversation continues
+    pub thread: Option<String>,
+    /// Whether the reply started with `!answer`, ending a conversation
+    pub is_final: bool,
+}
+
+impl Answer {
+    /// Reads a reply, recognizing `!skip`, `!idk` and `!later [wait]` at its
+    /// start, after an optional `!answer`
+    pub fn from_reply(text: impl Into<String>, responder: Option<String>) -> Self {
+        let text = text.into();
+        if let Some(rest) = Self::final_text(&text) {
+            return Self {
+                is_final: true,
+                ..Self::from_reply(rest, responder)
+            };
+        }
+        let answered_at = Utc::now();
+        let trimmed = text.trim_start();
+        let (command, rest) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+        let (status, text) = match command.to_lowercase().as_str() {
+            "!skip" => (AnswerStatus::Declined, rest.trim().to_string()),
+            "!idk" => (AnswerStatus::Unknown, rest.trim().to_string()),
+            "!later" => {
+                let (wait, note) = rest.trim().split_once(char::is_whitespace).unwrap_or((rest.trim(), ""));
+                match parse_duration(wait).ok().and_then(|wait| chrono::Duration::from_std(wait).ok()) {
+                    Some(wait) => (
+                        AnswerStatus::Deferred {
+                            until: Some(answered_at + wait),
+                        },
+                        note.trim().to_string(),
+                    ),
+                    None => (AnswerStatus::Deferred { until: None }, rest.trim().to_string()),
+                }
+            }
+            _ => (AnswerStatus::Answered, text),
+        };
+        Self {
+            status,
+            text,
+            responder,
+            answered_at,
+            attachments: Vec::new(),
+            thread: None,
+            is_final: false,
+        }
+    }
+
+    /// The result when nobody answered within `after`
+    pub fn timed_out(after: std::time::Duration) -> Self {
+        Self {
+            status: AnswerStatus::TimedOut { after },
+            text: String::new(),
+            responder: None,
+            answered_at: Utc::now(),
+            attachments: Vec::new(),
+            thread: None,
+            is_final: true,
+        }
+    }
+
+    /// The reply after a leading `!answer`, or `None` when it doesn't start with one
+    pub fn final_text(reply: &str) -> Option<&str> {
+        let trimmed = reply.trim_start();
+        let (command, rest) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+        command.eq_ignore_ascii_case(FINAL_ANSWER).then(|| rest.trim())
+    }
+
+    /// The reply as shown to the agent. Anything but an answer is spelled out
+    /// so the model knows whether to move on, work around it or ask again.
+    pub fn into_tool_result(self) -> CallToolResult {
+        let mut meta = serde_json::Map::new();
+        meta.insert("status".to_string(), self.status.name().into());
+        meta.insert("responder".to_string(), self.responder.clone().into());
+        meta.insert("answered_at".to_string(), self.answered_at.to_rfc3339().into());
+        if let AnswerStatus::Deferred { until: Some(until) } = &self.status {
+            meta.insert("deferred_until".to_string(), until.to_rfc3339().into());
+        }
+        if let AnswerStatus::Clarification { conversation_id } = &self.status {
+            meta.insert("conversation_id".to_string(), (*conversation_id).into());
+        }
+        let note = |text: &str| match text.trim() {
+            "" => String::new(),
+            note => format!(" They added: \"{note}\"."),
+        };
+        let text = match &self.status {
+            AnswerStatus::Answered => self.text,
+            AnswerStatus::Declined => format!(
+                "The human declined to answer this question.{} Don't ask it again; make a reasonable choice yourself or continue without it.",
+                note(&self.text)
+            ),
+            AnswerStatus::Unknown => format!(
+                "The human doesn't know the answer.{} Find it another way or make a reasonable assumption and say so.",
+                note(&self.text)
+            ),
+            AnswerStatus::Deferred { until: Some(until) } => format!(
+                "The human will answer later, after {}.{} Continue with other work and ask again no earlier than that.",
+                until.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                note(&self.text)
+            ),
+            AnswerStatus::Deferred { until: None } => format!(
+                "The human will answer later.{} Continue with other work and ask again later.",
+                note(&self.text)
+            ),
+            AnswerStatus::Clarification { conversation_id } => format!(
+                "The human asked a question back instead of answering: \"{}\". Respond with `reply_to_human` using conversation_id {conversation_id}; it returns their answer or their next question.",
+                self.text.trim()
+            ),
+            AnswerStatus::TimedOut { after } => format!(
+                "Nobody answered within {}s, so the question was withdrawn. Continue without the answer if you can, making a reasonable choice and saying so, or ask again later.",
+                after.as_secs()
+            ),
+        };
+        let mut content = vec![TextContent::new(text, None).into()];
+        content.extend(self.attachments.into_iter().map(Attachment::into_content));
+        CallToolResult {
+            content,
+            is_error: None,
+            meta: Some(meta),
+        }
+    }
+}
+
+/// Asks `question` until the reply fits the answer it expects, sending each
+/// one that doesn't back in its thread. `!skip`, `!idk` and `!later` are
+/// always accepted, and so are questions back in a conversation. A reply the
+/// human already gave is checked before asking.
+async fn ask_expecting(human: &dyn Human, question: &Question, mut given: Option<Answer>) -> anyhow::Result<Answer> {
+    let mut asked = question.clone();
+    let mut unreadable = Unreadable::default();
+    loop {
+        let mut answer = match given.take() {
+            Some(answer) => answer,
+            None => human.ask(&asked).await?,
+        };
+        let Some(expected) = &question.expected else {
+            return Ok(answer);
+        };
+        if answer.status != AnswerStatus::Answered || (question.conversation && !answer.is_final) {
+            return Ok(answer);
+        }
+        match expected.check(&answer.text, false) {
+            Ok(text) => {
+                answer.text = text;
+                return Ok(answer);
+            }
+            Err(problem) => {
+                unreadable.record(&problem)?;
+                asked.thread = answer.thread;
+                asked.attachments.clear();
+                asked.text = retry_text(&problem);
+            }
+        }
+    }
 }
 
 #[mcp_tool(
@@ -22,15 +370,487 @@ pub trait Human: Send + Sync + 'static {
 pub struct AskHumanTool {
     /// The question to ask the human. Be specific and provide context to help the human understand what information you need.
     question: String,
+    /// Files giving context for the question, such as code, diffs or logs. Put long content here rather than in the question text.
+    attachments: Option<Vec<QuestionAttachment>>,
+    /// Short label grouping related questions into the same thread, e.g. `database-migration`.
+    topic: Option<String>,
+    /// Up to 10 choices, which the human can pick with a single tap by reacting with their number. The answer is the text of the chosen option.
+    options: Option<Vec<String>>,
+    /// Who should answer, e.g. `infra`, `design` or `legal`. Must be one of the audiences listed in the server instructions; the default human is asked when omitted.
+    audience: Option<String>,
+    /// The kind of answer needed. Replies that don't fit are sent back to the human to correct, so the answer returned always fits.
+    expect: Option<ExpectedAnswer>,
+    /// Let the human ask questions back before answering. Their questions come back with status `clarification` and a `conversation_id` to respond to with `reply_to_human`.
+    conversation: Option<bool>,
+    /// How long to wait for an answer, e.g. `30m` or `2h`. Past it the question is withdrawn and the result has status `timed_out`. The server's configured timeout applies when omitted.
+    timeout: Option<String>,
 }
 impl AskHumanTool {
+    pub async fn call_tool(&self, human: &dyn Human, conversations: &Conversations) -> Result<CallToolResult, CallToolError> {
+        let options = self.options.clone().unwrap_or_default();
+        if options.len() > NUMBER_EMOJI.len() {
+            return Err(CallToolError(
+                anyhow::anyhow!("At most {} options can be offered", NUMBER_EMOJI.len()).into_boxed_dyn_error(),
+            ));
+        }
+        let expected = self
+            .expect
+            .as_ref()
+            .map(Expectation::try_from)
+            .transpose()
+            .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
+        let timeout = self
+            .timeout
+            .as_deref()
+            .map(parse_duration)
+            .transpose()
+            .map_err(|e| CallToolError(anyhow::anyhow!(e).into_boxed_dyn_error()))?;
+        let question = Question {
+            text: self.question.clone(),
+            attachments: self.attachments.clone().unwrap_or_default(),
+            topic: self.topic.clone(),
+            options,
+            audience: self.audience.clone(),
+            expected,
+            conversation: self.conversation.unwrap_or(false),
+            thread: None,
+            steps: Vec::new(),
+            yes_or_no: asks_yes_or_no(&self.question),
+            timeout,
+        };
+        let answer = ask_expecting(human, &question, None)
+            .await
+            .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
+        Ok(conversations.settle(question, answer, None).await.into_tool_result())
+    }
+}
+
+#[mcp_tool(
+    name = "reply_to_human",
+    description = "Respond to a question the human asked back about an earlier `ask_human` question, in the same thread, and wait for their answer or their next question",
+    idempotent_hint = false,
+    destructive_hint = false,
+    open_world_hint = false,
+    read_only_hint = false
+)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ReplyToHumanTool {
+    /// The `conversation_id` returned with the human's question.
+    conversation_id: u64,
+    /// Your response to the human's question.
+    message: String,
+}
+impl ReplyToHumanTool {
+    pub async fn call_tool(&self, human: &dyn Human, conversations: &Conversations) -> Result<CallToolResult, CallToolError> {
+        let Some(original) = conversations.get(self.conversation_id).await else {
+            return Err(CallToolError(
+                anyhow::anyhow!(
+                    "Conversation {} is not waiting for a reply; it has ended, expired after a day without a reply or never started",
+                    self.conversation_id
+                )
+                .into_boxed_dyn_error(),
+            ));
+        };
+        let question = Question {
+            text: self.message.clone(),
+            attachments: Vec::new(),
+            yes_or_no: asks_yes_or_no(&self.message),
+            ..original
+        };
+        // When the human already gave their final answer in the thread, the reply isn't posted
+        let early_answer = conversations.take_early_answer(self.conversation_id).await;
+        let answer = ask_expecting(human, &question, early_answer)
+            .await
+            .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
+        Ok(conversations
+            .settle(question, answer, Some(self.conversation_id))
+            .await
+            .into_tool_result())
+    }
+}
+
+#[mcp_tool(
+    name = "ask_human_batch",
+    description = "Ask a human several related questions at once in a single numbered message. Returns one entry per question with its status (`answered`, `declined`, `unknown`, `deferred`, `timed_out` or `unanswered`) and answer, once every question has one or the human ends the batch.",
+    idempotent_hint = false,
+    destructive_hint = false,
+    open_world_hint = false,
+    read_only_hint = false
+)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct AskHumanBatchTool {
+    /// The questions, in the order they should be numbered. Each should make sense on its own.
+    questions: Vec<String>,
+    /// Short label grouping related questions into the same thread, e.g. `database-migration`.
+    topic: Option<String>,
+    /// Who should answer, as for `ask_human`.
+    audience: Option<String>,
+}
+impl AskHumanBatchTool {
+    pub async fn call_tool(&self, human: &dyn Human) -> Result<CallToolResult, CallToolError> {
+        if self.questions.is_empty() {
+            return Err(CallToolError(
+                anyhow::anyhow!("Give at least one question").into_boxed_dyn_error(),
+            ));
+        }
+        let mut batch = Batch::new(self.questions.clone());
+        let mut question = Question {
+            text: batch.message(),
+            topic: self.topic.clone(),
+            audience: self.audience.clone(),
+            ..Default::default()
+        };
+        // Follow-ups go to the same thread until every question is settled
+        let mut unreadable = Unreadable::default();
+        loop {
+            let answer = human
+                .ask(&question)
+                .await
+                .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
+            question.thread = answer.thread.clone();
+            let problem = batch.record(answer).err();
+            if batch.is_finished() {
+                return Ok(batch.into_tool_result());
+            }
+            if let Some(problem) = &problem {
+                unreadable
+                    .record(problem)
+                    .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
+            }
+            question.text = batch.follow_up(problem.as_deref());
+        }
+    }
+}
+
+#[mcp_tool(
+    name = "review_plan",
+    description = "Show the human your multi-step plan as a checklist they can approve, reject, edit or comment on step by step, before you carry it out. Returns the `decision` (`approved`, `changes_requested` or `rejected`) and every step with its `status` (`approved`, `rejected`, `edited` or `commented`), the step as it should be carried out, and any comment.",
+    idempotent_hint = false,
+    destructive_hint = false,
+    open_world_hint = false,
+    read_only_hint = false
+)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ReviewPlanTool {
+    /// What the plan achieves, e.g. `Plan for migrating the users table`.
+    title: Option<String>,
+    /// The steps in the order you would carry them out, each a single action.
+    steps: Vec<String>,
+    /// Short label grouping related questions into the same thread, e.g. `database-migration`.
+    topic: Option<String>,
+    /// Who should review the plan, as for `ask_human`.
+    audience: Option<String>,
+}
+impl ReviewPlanTool {
     pub async fn call_tool(&self, human: &dyn Human) -> Result<CallToolResult, CallToolError> {
-        let answer = human
-            .ask(&self.question)
+        if self.steps.is_empty() {
+            return Err(CallToolError(
+                anyhow::anyhow!("Give at least one step").into_boxed_dyn_error(),
+            ));
+        }
+        let plan = Plan::new(self.title.clone(), self.steps.clone());
+        let mut question = Question {
+            text: plan.message(),
+            topic: self.topic.clone(),
+            audience: self.audience.clone(),
+            steps: self.steps.clone(),
+            yes_or_no: true,
+            ..Default::default()
+        };
+        // Replies that can't be matched to the steps are sent back in the same thread
+        let mut unreadable = Unreadable::default();
+        loop {
+            let answer = human
+                .ask(&question)
+                .await
+                .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
+            if answer.status != AnswerStatus::Answered {
+                return Ok(answer.into_tool_result());
+            }
+            match plan.review(&answer.text) {
+                Ok(review) => return Ok(review.into_tool_result()),
+                Err(problem) => {
+                    unreadable
+                        .record(&problem)
+                        .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
+                    question.thread = answer.thread;
+                    question.text = format!("⚠️ {problem} Please review the plan again.");
+                }
+            }
+        }
+    }
+}
+
+#[mcp_tool(
+    name = "request_review",
+    description = "Ask a human to review a code change given as a unified diff. Returns their `decision` (`approved`, `changes_requested` or `commented`), any general `body`, and `comments` with the `path`, `line` (in the new version of the file) and `body` of each.",
+    idempotent_hint = false,
+    destructive_hint = false,
+    open_world_hint = false,
+    read_only_hint = false
+)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct RequestReviewTool {
+    /// The change as a unified diff, e.g. the output of `git diff`.
+    diff: String,
+    /// What the change is, e.g. `the retry logic for uploads`.
+    title: Option<String>,
+    /// Why the change was made and anything the reviewer should look at closely.
+    description: Option<String>,
+    /// Short label grouping related questions into the same thread, e.g. `database-migration`.
+    topic: Option<String>,
+    /// Who should review the change, as for `ask_human`.
+    audience: Option<String>,
+}
+impl RequestReviewTool {
+    pub async fn call_tool(&self, human: &dyn Human, pages: Option<&ReviewPages>) -> Result<CallToolResult, CallToolError> {
+        let files = changed_files(&self.diff);
+        if files.is_empty() {
+            return Err(CallToolError(
+                anyhow::anyhow!("`diff` doesn't change any files; pass a unified diff such as the output of `git diff`")
+                    .into_boxed_dyn_error(),
+            ));
+        }
+        let title = self.title.as_deref().unwrap_or("Review request");
+        let web_view = match pages {
+            Some(pages) => Some(pages.open(title, &self.diff).await),
+            None => None,
+        };
+        // The page comes down with the tool call, even when it is cancelled
+        let page_closed = WithdrawnOnDrop::new({
+            let page = pages.cloned().zip(web_view.clone());
+            move || {
+                if let Some((pages, url)) = page {
+                    tokio::spawn(async move { pages.close(&url).await });
+                }
+            }
+        });
+        let mut question = Question {
+            text: crate::review::message(self.title.as_deref(), self.description.as_deref(), &files, web_view.as_deref()),
+            attachments: vec![QuestionAttachment {
+                filename: "changes.diff".to_string(),
+                content: self.diff.clone(),
+                language: Some("diff".to_string()),
+            }],
+            topic: self.topic.clone(),
+            audience: self.audience.clone(),
+            yes_or_no: true,
+            ..Default::default()
+        };
+        // Comments that can't be matched to the diff are sent back in the same thread
+        let mut unreadable = Unreadable::default();
+        let result = loop {
+            let answer = match human.ask(&question).await {
+                Ok(answer) => answer,
+                Err(e) => break Err(e),
+            };
+            if answer.status != AnswerStatus::Answered {
+                break Ok(answer.into_tool_result());
+            }
+            match Review::parse(&answer.text, &files) {
+                Ok(review) => break Ok(review.into_tool_result()),
+                Err(problem) => {
+                    if let Err(e) = unreadable.record(&problem) {
+                        break Err(e);
+                    }
+                    question.thread = answer.thread;
+                    question.attachments.clear();
+                    question.text = format!("⚠️ {problem} Please reply again.");
+                }
+            }
+        };
+        page_closed.disarm();
+        if let (Some(pages), Some(url)) = (pages, &web_view) {
+            pages.close(url).await;
+        }
+        result.map_err(|e| CallToolError(e.into_boxed_dyn_error()))
+    }
+}
+
+#[mcp_tool(
+    name = "start_new_thread",
+    description = "Start a fresh discussion thread for the following questions, e.g. when moving on to an unrelated task",
+    idempotent_hint = true,
+    destructive_hint = false,
+    open_world_hint = false,
+    read_only_hint = false
+)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct StartNewThreadTool {
+    /// Title for the new thread. Defaults to the text of the next question.
+    title: Option<String>,
+    /// When questions are grouped by topic, the topic whose thread should be replaced.
+    topic: Option<String>,
+}
+impl StartNewThreadTool {
+    pub async fn call_tool(&self, human: &dyn Human) -> Result<CallToolResult, CallToolError> {
+        human
+            .start_new_thread(self.title.as_deref(), self.topic.as_deref())
             .await
             .map_err(|e| CallToolError(e.into_boxed_dyn_error()))?;
-        Ok(CallToolResult::text_content(answer, None))
+        Ok(CallToolResult::text_content(
+            "The next question will be posted in a new thread.".to_string(),
+            None,
+        ))
     }
 }
 
-tool_box!(HumanTools, [AskHumanTool]);
+pub use toolbox::HumanTools;
+
+mod toolbox {
+    // The variants are named after the tools, which all end in `Tool`
+    #![allow(clippy::enum_variant_names)]
+    use super::*;
+
+    tool_box!(
+        HumanTools,
+        [
+            AskHumanTool,
+            AskHumanBatchTool,
+            ReplyToHumanTool,
+            ReviewPlanTool,
+            RequestReviewTool,
+            StartNewThreadTool
+        ]
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gives the same reply to every question, like a script with a fallback
+    struct Repeating(&'static str);
+
+    #[async_trait::async_trait]
+    impl Human for Repeating {
+        async fn ask(&self, _question: &Question) -> anyhow::Result<Answer> {
+            Ok(Answer::from_reply(self.0, None))
+        }
+
+        async fn start_new_thread(&self, _title: Option<&str>, _topic: Option<&str>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn collect_votes(&self, _question: &Question, _policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+            anyhow::bail!("not used")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_on_replies_that_cant_be_read() {
+        let batch = AskHumanBatchTool {
+            questions: vec!["Database?".to_string(), "Region?".to_string()],
+            topic: None,
+            audience: None,
+        };
+        let error = batch.call_tool(&Repeating("yes")).await.unwrap_err();
+        assert!(error.to_string().starts_with("Gave up after 5 replies"), "{error}");
+
+        let plan = ReviewPlanTool {
+            title: None,
+            steps: vec!["Back up the database".to_string()],
+            topic: None,
+            audience: None,
+        };
+        assert!(plan.call_tool(&Repeating("7. ok")).await.is_err());
+        assert!(plan.call_tool(&Repeating("ok")).await.is_ok());
+    }
+
+    /// Replies with each of its answers in turn
+    struct Replies(std::sync::Mutex<Vec<&'static str>>);
+
+    #[async_trait::async_trait]
+    impl Human for Replies {
+        async fn ask(&self, _question: &Question) -> anyhow::Result<Answer> {
+            Ok(Answer::from_reply(self.0.lock().unwrap().remove(0), None))
+        }
+
+        async fn start_new_thread(&self, _title: Option<&str>, _topic: Option<&str>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn collect_votes(&self, _question: &Question, _policy: &ApprovalPolicy) -> anyhow::Result<Verdict> {
+            anyhow::bail!("not used")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_asks_until_the_answer_fits() {
+        let question = Question {
+            text: "When can we deploy?".to_string(),
+            expected: Some(Expectation::Date),
+            ..Default::default()
+        };
+        let human = Replies(std::sync::Mutex::new(vec!["tomorrowish", " 2026-10-20 "]));
+        assert_eq!(ask_expecting(&human, &question, None).await.unwrap().text, "2026-10-20");
+        assert!(human.0.lock().unwrap().is_empty());
+
+        let human = Replies(std::sync::Mutex::new(vec!["!skip not my call"]));
+        assert_eq!(ask_expecting(&human, &question, None).await.unwrap().status, AnswerStatus::Declined);
+        assert!(ask_expecting(&Repeating("soon"), &question, None).await.is_err());
+    }
+
+    fn attachment(filename: &str, content: &str) -> QuestionAttachment {
+        QuestionAttachment {
+            filename: filename.to_string(),
+            content: content.to_string(),
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_short_attachments_are_inlined() {
+        let long_log = "line\n".repeat(100);
+        let question = Question {
+            text: "Does this look right?".to_string(),
+            attachments: vec![attachment("main.rs", "fn main() {}\n"), attachment("build.log", &long_log)],
+            ..Default::default()
+        };
+        assert_eq!(
+            question.message_body(),
+            "Does this look right?\n`main.rs`\n```rust\nfn main() {}\n```"
+        );
+        let uploads = question.uploads().map(|a| a.filename.as_str()).collect::<Vec<_>>();
+        assert_eq!(uploads, ["build.log"]);
+    }
+
+    #[test]
+    fn test_answer_status() {
+        let answer = Answer::from_reply("Use Postgres", Some("U1".to_string()));
+        assert_eq!((answer.status.clone(), answer.text.as_str()), (AnswerStatus::Answered, "Use Postgres"));
+        let result = answer.into_tool_result();
+        assert_eq!(result.meta.unwrap()["responder"], "U1");
+
+        let answer = Answer::from_reply("!SKIP not my area", None);
+        assert_eq!((answer.status, answer.text.as_str()), (AnswerStatus::Declined, "not my area"));
+        assert_eq!(Answer::from_reply("!idk", None).status, AnswerStatus::Unknown);
+
+        let answer = Answer::from_reply("!later 2h after standup", None);
+        let AnswerStatus::Deferred { until: Some(until) } = answer.status else {
+            panic!("not deferred: {:?}", answer.status);
+        };
+        assert_eq!(until - answer.answered_at, chrono::Duration::hours(2));
+        assert_eq!(answer.text, "after standup");
+        let result = answer.into_tool_result();
+        let text = result.content[0].as_text_content().unwrap().text.clone();
+        assert!(text.starts_with("The human will answer later, after ") && text.ends_with(". They added: \"after standup\". Continue with other work and ask again no earlier than that."), "{text}");
+        assert_eq!(
+            Answer::from_reply("!later when I'm back", None).status,
+            AnswerStatus::Deferred { until: None }
+        );
+    }
+
+    #[test]
+    fn test_options_are_numbered() {
+        let question = Question {
+            text: "Which database?".to_string(),
+            options: vec!["Postgres".to_string(), "SQLite".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            question.message_body(),
+            format!("Which database?\n{} Postgres\n{} SQLite", NUMBER_EMOJI[0], NUMBER_EMOJI[1])
+        );
+    }
+}
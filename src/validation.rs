@@ -0,0 +1,192 @@
+//! Checks replies against the kind of answer the agent expects, so the human
+//! is asked to correct a reply instead of the agent asking all over again
+
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_mcp_sdk::macros::JsonSchema;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::tools::{Answer, AnswerStatus, FINAL_ANSWER};
+
+/// The kind of answer the agent expects, as given in the tool call
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ExpectedAnswer {
+    /// One of `integer`, `number`, `date` (YYYY-MM-DD), `url`, `regex` or `one_of`.
+    kind: String,
+    /// Smallest accepted value for `integer` and `number`.
+    min: Option<f64>,
+    /// Largest accepted value for `integer` and `number`.
+    max: Option<f64>,
+    /// Pattern the whole answer must match, for `regex`.
+    pattern: Option<String>,
+    /// Accepted answers for `one_of`, matched case-insensitively.
+    choices: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expectation {
+    Integer { min: Option<f64>, max: Option<f64> },
+    Number { min: Option<f64>, max: Option<f64> },
+    Date,
+    Url,
+    Pattern { regex: Regex, pattern: String },
+    OneOf(Vec<String>),
+}
+
+impl TryFrom<&ExpectedAnswer> for Expectation {
+    type Error = anyhow::Error;
+
+    fn try_from(expected: &ExpectedAnswer) -> anyhow::Result<Self> {
+        let (min, max) = (expected.min, expected.max);
+        if let (Some(min), Some(max)) = (min, max) {
+            anyhow::ensure!(min <= max, "`min` must not be greater than `max`");
+        }
+        Ok(match expected.kind.as_str() {
+            "integer" => Self::Integer { min, max },
+            "number" => Self::Number { min, max },
+            "date" => Self::Date,
+            "url" => Self::Url,
+            "regex" => {
+                let Some(pattern) = &expected.pattern else {
+                    anyhow::bail!("`regex` answers need a `pattern`");
+                };
+                let regex = Regex::new(&format!("^(?:{pattern})$"))
+                    .map_err(|e| anyhow::anyhow!("Invalid `pattern`: {e}"))?;
+                Self::Pattern {
+                    regex,
+                    pattern: pattern.clone(),
+                }
+            }
+            "one_of" => match &expected.choices {
+                Some(choices) if !choices.is_empty() => Self::OneOf(choices.clone()),
+                _ => anyhow::bail!("`one_of` answers need `choices`"),
+            },
+            kind => anyhow::bail!(
+                "Unknown answer kind `{kind}`; use one of `integer`, `number`, `date`, `url`, `regex` or `one_of`"
+            ),
+        })
+    }
+}
+
+impl Expectation {
+    /// What the human is asked to reply with, shown under the question
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Integer { min, max } => format!("a whole number{}", range(*min, *max)),
+            Self::Number { min, max } => format!("a number{}", range(*min, *max)),
+            Self::Date => "a date like 2026-10-18".to_string(),
+            Self::Url => "a link starting with https://".to_string(),
+            Self::Pattern { pattern, .. } => format!("text matching `{pattern}`"),
+            Self::OneOf(choices) => format!("one of {}", choices.join(", ")),
+        }
+    }
+
+    /// The reply as the agent should see it, or why it doesn't fit. `!skip`,
+    /// `!idk` and `!later` are never checked, and neither are questions back
+    /// in a `conversation`.
+    pub fn check(&self, reply: &str, conversation: bool) -> Result<String, String> {
+        let (marker, text) = match Answer::final_text(reply) {
+            Some(text) => (format!("{FINAL_ANSWER} "), text),
+            None if conversation => return Ok(reply.to_string()),
+            None => (String::new(), reply.trim()),
+        };
+        if Answer::from_reply(text, None).status != AnswerStatus::Answered {
+            return Ok(reply.to_string());
+        }
+        let fits = match self {
+            Self::Integer { min, max } => text
+                .parse::<i64>()
+                .ok()
+                .filter(|value| in_range(*value as f64, *min, *max))
+                .map(|value| value.to_string()),
+            Self::Number { min, max } => text
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite() && in_range(*value, *min, *max))
+                .map(|_| text.to_string()),
+            Self::Date => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .map(|date| date.to_string()),
+            Self::Url => Url::parse(text.trim_matches(['<', '>']))
+                .ok()
+                .filter(|url| matches!(url.scheme(), "http" | "https"))
+                .map(String::from),
+            Self::Pattern { regex, .. } => regex.is_match(text).then(|| text.to_string()),
+            Self::OneOf(choices) => choices
+                .iter()
+                .find(|choice| choice.trim().eq_ignore_ascii_case(text))
+                .cloned(),
+        };
+        fits.map(|text| format!("{marker}{text}"))
+            .ok_or_else(|| format!("“{text}” isn't {}.", self.describe()))
+    }
+}
+
+/// Posted in the thread when a reply doesn't fit, before waiting for another one
+pub fn retry_text(problem: &str) -> String {
+    format!("⚠️ {problem} Please reply again, or use `!skip` if you can't answer.")
+}
+
+fn in_range(value: f64, min: Option<f64>, max: Option<f64>) -> bool {
+    min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+}
+
+fn range(min: Option<f64>, max: Option<f64>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!(" from {min} to {max}"),
+        (Some(min), None) => format!(" of at least {min}"),
+        (None, Some(max)) => format!(" of at most {max}"),
+        (None, None) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expect(json: serde_json::Value) -> Expectation {
+        let expected: ExpectedAnswer = serde_json::from_value(json).unwrap();
+        Expectation::try_from(&expected).unwrap()
+    }
+
+    #[test]
+    fn test_checks_replies() {
+        let integer = expect(serde_json::json!({ "kind": "integer", "min": 1, "max": 10 }));
+        assert_eq!(integer.check(" 7 ", false), Ok("7".to_string()));
+        assert_eq!(integer.check("11", false), Err("“11” isn't a whole number from 1 to 10.".to_string()));
+        assert!(integer.check("7.5", false).is_err());
+
+        let date = expect(serde_json::json!({ "kind": "date" }));
+        assert_eq!(date.check("2026-10-19", false), Ok("2026-10-19".to_string()));
+        assert_eq!(date.check("tomorrowish", false), Err("“tomorrowish” isn't a date like 2026-10-18.".to_string()));
+        // Status commands pass through unchecked
+        assert_eq!(date.check("!later 1h", false), Ok("!later 1h".to_string()));
+        // In a conversation only the final answer is checked
+        assert_eq!(date.check("which timezone?", true), Ok("which timezone?".to_string()));
+        assert_eq!(date.check("!answer 2026-10-19", true), Ok("!answer 2026-10-19".to_string()));
+        assert!(date.check("!answer soon", true).is_err());
+
+        let url = expect(serde_json::json!({ "kind": "url" }));
+        assert_eq!(url.check("<https://example.com/a>", false), Ok("https://example.com/a".to_string()));
+        assert!(url.check("example.com", false).is_err());
+
+        let pattern = expect(serde_json::json!({ "kind": "regex", "pattern": "[A-Z]+-\\d+" }));
+        assert!(pattern.check("HITL-42", false).is_ok());
+        assert!(pattern.check("see HITL-42", false).is_err());
+
+        let one_of = expect(serde_json::json!({ "kind": "one_of", "choices": ["Staging", "Production"] }));
+        assert_eq!(one_of.check("production", false), Ok("Production".to_string()));
+        assert_eq!(one_of.check("prod", false), Err("“prod” isn't one of Staging, Production.".to_string()));
+    }
+
+    #[test]
+    fn test_rejects_bad_expectations() {
+        let parse = |json| Expectation::try_from(&serde_json::from_value::<ExpectedAnswer>(json).unwrap());
+        assert!(parse(serde_json::json!({ "kind": "regex" })).is_err());
+        assert!(parse(serde_json::json!({ "kind": "regex", "pattern": "(" })).is_err());
+        assert!(parse(serde_json::json!({ "kind": "one_of", "choices": [] })).is_err());
+        assert!(parse(serde_json::json!({ "kind": "integer", "min": 5, "max": 1 })).is_err());
+        assert!(parse(serde_json::json!({ "kind": "colour" })).is_err());
+    }
+}